@@ -0,0 +1,200 @@
+use std::fmt::Display;
+
+/// Byte order of a multi-byte capture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+/// The integer type a capture is decoded as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureType {
+    U8,
+    I8,
+    U16(Endian),
+    I16(Endian),
+    U32(Endian),
+    I32(Endian),
+    U64(Endian),
+    I64(Endian),
+}
+
+impl CaptureType {
+    /// Number of bytes covered by the capture.
+    pub fn size(&self) -> usize {
+        match self {
+            CaptureType::U8 | CaptureType::I8 => 1,
+            CaptureType::U16(_) | CaptureType::I16(_) => 2,
+            CaptureType::U32(_) | CaptureType::I32(_) => 4,
+            CaptureType::U64(_) | CaptureType::I64(_) => 8,
+        }
+    }
+
+    /// Parses a type name such as `u8`, `i32le` or `u16be`. Multi-byte types
+    /// without a suffix are little-endian.
+    pub(crate) fn parse(name: &str) -> Option<Self> {
+        let (base, endian) = if let Some(base) = name.strip_suffix("le") {
+            (base, Some(Endian::Little))
+        } else if let Some(base) = name.strip_suffix("be") {
+            (base, Some(Endian::Big))
+        } else {
+            (name, None)
+        };
+        let e = endian.unwrap_or(Endian::Little);
+
+        Some(match base {
+            "u8" if endian.is_none() => CaptureType::U8,
+            "i8" if endian.is_none() => CaptureType::I8,
+            "u16" => CaptureType::U16(e),
+            "i16" => CaptureType::I16(e),
+            "u32" => CaptureType::U32(e),
+            "i32" => CaptureType::I32(e),
+            "u64" => CaptureType::U64(e),
+            "i64" => CaptureType::I64(e),
+            _ => return None,
+        })
+    }
+
+    /// Decodes the capture from the start of `bytes`, which must hold at
+    /// least [`size`](Self::size) bytes.
+    pub(crate) fn decode(&self, bytes: &[u8]) -> Option<CaptureValue> {
+        macro_rules! read {
+            ($ty:ty, $endian:expr) => {{
+                let raw = bytes.get(..size_of::<$ty>())?.try_into().ok()?;
+                match $endian {
+                    Endian::Little => <$ty>::from_le_bytes(raw),
+                    Endian::Big => <$ty>::from_be_bytes(raw),
+                }
+            }};
+        }
+
+        Some(match *self {
+            CaptureType::U8 => CaptureValue::U8(*bytes.first()?),
+            CaptureType::I8 => CaptureValue::I8(*bytes.first()? as i8),
+            CaptureType::U16(e) => CaptureValue::U16(read!(u16, e)),
+            CaptureType::I16(e) => CaptureValue::I16(read!(i16, e)),
+            CaptureType::U32(e) => CaptureValue::U32(read!(u32, e)),
+            CaptureType::I32(e) => CaptureValue::I32(read!(i32, e)),
+            CaptureType::U64(e) => CaptureValue::U64(read!(u64, e)),
+            CaptureType::I64(e) => CaptureValue::I64(read!(i64, e)),
+        })
+    }
+}
+
+impl Display for CaptureType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (name, endian) = match self {
+            CaptureType::U8 => ("u8", None),
+            CaptureType::I8 => ("i8", None),
+            CaptureType::U16(e) => ("u16", Some(e)),
+            CaptureType::I16(e) => ("i16", Some(e)),
+            CaptureType::U32(e) => ("u32", Some(e)),
+            CaptureType::I32(e) => ("i32", Some(e)),
+            CaptureType::U64(e) => ("u64", Some(e)),
+            CaptureType::I64(e) => ("i64", Some(e)),
+        };
+        match endian {
+            None => write!(f, "{name}"),
+            Some(Endian::Little) => write!(f, "{name}le"),
+            Some(Endian::Big) => write!(f, "{name}be"),
+        }
+    }
+}
+
+/// A decoded capture value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureValue {
+    U8(u8),
+    I8(i8),
+    U16(u16),
+    I16(i16),
+    U32(u32),
+    I32(i32),
+    U64(u64),
+    I64(i64),
+}
+
+impl CaptureValue {
+    /// The value sign-extended or zero-extended to `i64`, following its type.
+    /// `u64` values above `i64::MAX` wrap around.
+    pub fn as_i64(&self) -> i64 {
+        match *self {
+            CaptureValue::U8(v) => v as i64,
+            CaptureValue::I8(v) => v as i64,
+            CaptureValue::U16(v) => v as i64,
+            CaptureValue::I16(v) => v as i64,
+            CaptureValue::U32(v) => v as i64,
+            CaptureValue::I32(v) => v as i64,
+            CaptureValue::U64(v) => v as i64,
+            CaptureValue::I64(v) => v,
+        }
+    }
+
+    /// The raw value zero-extended to `u64`, ignoring its sign.
+    pub fn as_u64(&self) -> u64 {
+        match *self {
+            CaptureValue::U8(v) => v as u64,
+            CaptureValue::I8(v) => v as u8 as u64,
+            CaptureValue::U16(v) => v as u64,
+            CaptureValue::I16(v) => v as u16 as u64,
+            CaptureValue::U32(v) => v as u64,
+            CaptureValue::I32(v) => v as u32 as u64,
+            CaptureValue::U64(v) => v,
+            CaptureValue::I64(v) => v as u64,
+        }
+    }
+}
+
+/// A single named capture of a match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capture<'p> {
+    pub(crate) name: &'p str,
+    pub(crate) offset: usize,
+    pub(crate) value: CaptureValue,
+}
+
+impl<'p> Capture<'p> {
+    pub fn name(&self) -> &'p str {
+        self.name
+    }
+
+    /// Offset of the first captured byte in the scanned data.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn value(&self) -> CaptureValue {
+        self.value
+    }
+}
+
+/// The decoded captures of a match, in pattern order.
+#[derive(Debug, Clone, Default)]
+pub struct Captures<'p> {
+    pub(crate) captures: Vec<Capture<'p>>,
+}
+
+impl<'p> Captures<'p> {
+    /// Looks up a capture by name.
+    pub fn get(&self, name: &str) -> Option<CaptureValue> {
+        self.capture(name).map(|c| c.value)
+    }
+
+    /// Looks up a capture by name, including its offset in the data.
+    pub fn capture(&self, name: &str) -> Option<&Capture<'p>> {
+        self.captures.iter().find(|c| c.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Capture<'p>> {
+        self.captures.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.captures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.captures.is_empty()
+    }
+}
@@ -1,17 +1,83 @@
-mod common;
+pub(crate) mod common;
+mod frequency;
 
 pub(crate) mod anchor;
+pub(crate) mod scalar;
 pub(crate) mod teddy;
 
-use crate::pattern::PatternId;
+pub use common::AnchorSet;
+pub use frequency::ByteFrequencies;
+
+use crate::{
+    pattern::{Pattern, PatternId},
+    resolve::Relative,
+};
 
 pub(crate) trait LookupEngine {
-    fn new<'s, I>(patterns: I) -> Self
+    /// Builds the engine, searching for each pattern through the anchor at
+    /// the same index.
+    fn new(patterns: &[Pattern], anchors: &[AnchorSet]) -> Self
     where
-        Self: Sized,
-        I: IntoIterator<Item = &'s str>;
+        Self: Sized;
 
     fn scan(&self, data: &[u8], on_match: &mut dyn FnMut(MatchedPattern) -> Scan);
+
+    fn kind(&self) -> EngineKind;
+
+    /// The instruction set the engine scans with.
+    fn simd(&self) -> SimdPath {
+        SimdPath::Scalar
+    }
+}
+
+/// The search algorithm a [`Hexpotter`](crate::Hexpotter) scans with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum EngineKind {
+    /// Teddy when the CPU has a SIMD path for it and the anchors fit in a
+    /// few dozen of its groups, Anchor otherwise.
+    #[default]
+    Auto,
+    /// SIMD fingerprint matching on the first bytes of each anchor. Falls
+    /// back to a scalar loop on CPUs without AVX2 or NEON.
+    Teddy,
+    /// An Aho-Corasick automaton over every anchor literal.
+    Anchor,
+    /// A portable byte-by-byte search with no SIMD or automaton, mainly
+    /// useful as a reference.
+    Scalar,
+}
+
+/// The instruction set an engine scans with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SimdPath {
+    Avx2,
+    Neon,
+    Scalar,
+}
+
+impl SimdPath {
+    /// The best path the current CPU supports.
+    pub(crate) fn detect() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("avx2") {
+            return SimdPath::Avx2;
+        }
+
+        #[cfg(target_arch = "aarch64")]
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            return SimdPath::Neon;
+        }
+
+        // NEON is optional on 32-bit ARM and runtime detection is not stable
+        // there, so only builds targeting it take the NEON path
+        #[cfg(all(target_arch = "arm", target_feature = "neon"))]
+        {
+            return SimdPath::Neon;
+        }
+
+        #[allow(unreachable_code)]
+        SimdPath::Scalar
+    }
 }
 
 #[repr(u8)]
@@ -25,6 +91,7 @@ pub enum Scan {
 pub struct MatchedPattern {
     pub(crate) start: usize,
     pub(crate) end: usize,
+    pub(crate) target: usize,
     pub(crate) pattern_id: PatternId,
 }
 
@@ -39,8 +106,35 @@ impl MatchedPattern {
         self.end
     }
 
+    /// The offset designated by the pattern's `^` marker, or [`start`](Self::start)
+    /// when the pattern has none.
+    #[inline(always)]
+    pub fn target(&self) -> usize {
+        self.target
+    }
+
     #[inline(always)]
     pub fn id(&self) -> PatternId {
         self.pattern_id
     }
+
+    /// Resolves a relative operand of the instruction starting at
+    /// [`target`](Self::target), returning the absolute offset it points to.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexpotter::{Hexpotter, Relative, Scan};
+    /// // lea rax, [rip+0x20]
+    /// let data = [0x48, 0x8D, 0x05, 0x20, 0x00, 0x00, 0x00];
+    /// let scanner = Hexpotter::new(["48 8D 05 ?? ?? ?? ??"]);
+    ///
+    /// scanner.scan(&data, |m| {
+    ///     assert_eq!(m.resolve(&data, Relative::new(3, 4, 7)), Some(0x27));
+    ///     Scan::Continue
+    /// });
+    /// ```
+    pub fn resolve(&self, data: &[u8], relative: Relative) -> Option<usize> {
+        relative.resolve(data, self.target)
+    }
 }
@@ -1,134 +1,140 @@
-use aho_corasick::{AhoCorasick, AhoCorasickKind, Match};
+use aho_corasick::{
+    Anchored, Input,
+    automaton::{Automaton, OverlappingState},
+    dfa::DFA,
+};
 use std::collections::HashMap;
 
 use crate::{
     engine::{
-        LookupEngine, MatchedPattern, Scan,
-        common::{self, PatternInfo},
+        EngineKind, LookupEngine, MatchedPattern, Scan,
+        common::{AnchorSet, Ordered, PatternInfo, PatternTable},
     },
-    pattern::PatternId,
+    pattern::Pattern,
 };
 
 pub struct Anchor {
-    ac: AhoCorasick,
+    dfa: DFA,
     pattern_map: HashMap<usize, Vec<PatternInfo>>,
-    all_values: Vec<u8>,
-    all_masks: Vec<u8>,
+    /// Length of the longest literal in the automaton.
+    longest: usize,
+    unanchored: Vec<PatternInfo>,
+    table: PatternTable,
 }
 
 impl LookupEngine for Anchor {
-    fn new<'s, I>(patterns: I) -> Self
+    fn new(patterns: &[Pattern], anchors: &[AnchorSet]) -> Self
     where
         Self: Sized,
-        I: IntoIterator<Item = &'s str>,
     {
-        let mut anchors = Vec::new();
+        let mut literals: Vec<&[u8]> = Vec::new();
+        let mut literal_ids: HashMap<&[u8], usize> = HashMap::new();
         let mut pattern_map: HashMap<usize, Vec<PatternInfo>> = HashMap::new();
 
-        let mut all_values = Vec::new();
-        let mut all_masks = Vec::new();
+        let mut unanchored = Vec::new();
+        let mut table = PatternTable::default();
 
-        for (index, pattern_str) in patterns.into_iter().enumerate() {
-            let (p_values, p_masks) = common::parse_hex_pattern(pattern_str);
-            let (anchor, offset) = common::find_best_anchor(&p_values, &p_masks);
+        for (index, pattern) in patterns.iter().enumerate() {
+            let anchor = &anchors[index];
+            let pat = table.push(index, pattern, anchor.offset);
+            if !anchor.is_anchored() {
+                unanchored.push(pat);
+            }
 
-            // Deduplicate Anchors
-            let ac_id = anchors
-                .iter()
-                .position(|x| x == &anchor)
-                .unwrap_or_else(|| {
-                    anchors.push(anchor.clone());
-                    anchors.len() - 1
+            for literal in &anchor.literals {
+                // Deduplicate Anchors
+                let ac_id = *literal_ids.entry(literal).or_insert_with(|| {
+                    literals.push(literal);
+                    literals.len() - 1
                 });
 
-            let data_offset = all_values.len();
-            let len = p_values.len();
-            all_values.extend_from_slice(&p_values);
-            all_masks.extend_from_slice(&p_masks);
-
-            let pat = PatternInfo {
-                id: index,
-                data_offset,
-                len,
-                anchor_offset: offset,
-            };
-
-            pattern_map.entry(ac_id).or_default().push(pat);
+                pattern_map.entry(ac_id).or_default().push(pat);
+            }
         }
 
-        let ac = AhoCorasick::builder()
-            .kind(Some(AhoCorasickKind::DFA))
-            .build(&anchors)
-            .expect("Failed to build Aho-Corasick");
+        let dfa = DFA::new(&literals).expect("Failed to build Aho-Corasick");
 
         Anchor {
-            ac,
+            dfa,
             pattern_map,
-            all_values,
-            all_masks,
+            longest: literals.iter().map(|l| l.len()).max().unwrap_or(0),
+            unanchored,
+            table,
         }
     }
 
     fn scan(&self, data: &[u8], on_match: &mut dyn FnMut(MatchedPattern) -> Scan) {
-        for mat in self.ac.find_overlapping_iter(data) {
-            let ac_id = mat.pattern().as_usize();
-
-            if let Some(candidates) = self.pattern_map.get(&ac_id) {
-                for pat in candidates {
-                    if self.verify_match(data, &mat, pat, on_match) == Scan::Stop {
-                        return;
-                    }
-                }
-            }
+        let mut out = Ordered::new(&self.table, on_match);
+        let scan = if self.unanchored.is_empty() {
+            self.scan_literals(data, &mut out)
+        } else {
+            self.scan_positions(data, &mut out)
+        };
+        if scan == Scan::Continue {
+            out.finish();
         }
     }
+
+    fn kind(&self) -> EngineKind {
+        EngineKind::Anchor
+    }
 }
 
 impl Anchor {
-    #[inline(always)]
-    fn verify_match<F>(
-        &self,
-        data: &[u8],
-        anchor_match: &Match,
-        pat: &PatternInfo,
-        on_match: &mut F,
-    ) -> Scan
-    where
-        F: FnMut(MatchedPattern) -> Scan + ?Sized,
-    {
-        let match_start = anchor_match.start();
+    /// Lets the automaton skip ahead to each literal, which only works when
+    /// every pattern has one.
+    fn scan_literals(&self, data: &[u8], out: &mut Ordered) -> Scan {
+        let input = Input::new(data);
+        let mut state = OverlappingState::start();
+        loop {
+            self.dfa
+                .try_find_overlapping(&input, &mut state)
+                .expect("unanchored overlapping search");
+            let Some(mat) = state.get_match() else {
+                return Scan::Continue;
+            };
 
-        // bounds Checks
-        if match_start < pat.anchor_offset {
-            return Scan::Continue;
-        }
-        let start_index = match_start - pat.anchor_offset;
-        let end_index = start_index + pat.len;
-        if end_index > data.len() {
-            return Scan::Continue;
+            // literals are found by where they end, so the ones still to
+            // come start at most `longest` bytes before this one ends
+            if out.advance(mat.end().saturating_sub(self.longest)) == Scan::Stop {
+                return Scan::Stop;
+            }
+            self.verify(data, mat.start(), mat.pattern().as_usize(), out);
         }
+    }
 
-        let data_slice = &data[start_index..end_index];
-        let pat_vals = &self.all_values[pat.data_offset..pat.data_offset + pat.len];
-        let pat_masks = &self.all_masks[pat.data_offset..pat.data_offset + pat.len];
-
-        let mut is_match = true;
-
-        for i in 0..pat.len {
-            if (data_slice[i] & pat_masks[i]) != pat_vals[i] {
-                is_match = false;
-                break;
+    /// Steps the automaton one byte at a time, since patterns without a
+    /// literal are verified at every position anyway.
+    fn scan_positions(&self, data: &[u8], out: &mut Ordered) -> Scan {
+        let mut sid = self
+            .dfa
+            .start_state(Anchored::No)
+            .expect("unanchored start state");
+
+        for (pos, &byte) in data.iter().enumerate() {
+            // literals still to come end after `pos`
+            if out.advance(pos.min((pos + 1).saturating_sub(self.longest))) == Scan::Stop {
+                return Scan::Stop;
+            }
+            self.table.find_all(data, pos, &self.unanchored, out);
+
+            sid = self.dfa.next_state(Anchored::No, sid, byte);
+            if self.dfa.is_match(sid) {
+                for index in 0..self.dfa.match_len(sid) {
+                    let literal = self.dfa.match_pattern(sid, index);
+                    let start = pos + 1 - self.dfa.pattern_len(literal);
+                    self.verify(data, start, literal.as_usize(), out);
+                }
             }
         }
+        Scan::Continue
+    }
 
-        if is_match {
-            return on_match(MatchedPattern {
-                start: start_index,
-                end: end_index,
-                pattern_id: PatternId(pat.id),
-            });
+    /// Verifies the patterns anchored on `literal`, found at `start`.
+    #[inline(always)]
+    fn verify(&self, data: &[u8], start: usize, literal: usize, out: &mut Ordered) {
+        if let Some(candidates) = self.pattern_map.get(&literal) {
+            self.table.find_all(data, start, candidates, out);
         }
-
-        Scan::Continue
     }
 }
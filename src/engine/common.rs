@@ -1,64 +1,411 @@
+use std::{collections::VecDeque, ops::Range};
+
+use crate::{
+    engine::{ByteFrequencies, MatchedPattern, Scan},
+    pattern::{ByteClass, Jumps, Pattern, PatternId},
+};
+
 #[derive(Clone, Copy, Debug)]
 pub struct PatternInfo {
     pub id: usize,
     pub len: usize,
     pub data_offset: usize,
     pub anchor_offset: usize,
+    pub target_offset: usize,
+    pub class_offset: usize,
+    pub class_len: usize,
+    /// Index of the pattern among the table's gapped patterns, if it has
+    /// variable-length jumps.
+    pub gapped: Option<usize>,
 }
 
-pub fn parse_hex_pattern(pattern: &str) -> (Vec<u8>, Vec<u8>) {
-    let mut values = Vec::with_capacity(pattern.len() / 2);
-    let mut masks = Vec::with_capacity(pattern.len() / 2);
-
-    for part in pattern.split_whitespace() {
-        if part == "??" {
-            values.push(0x00);
-            masks.push(0x00);
-        } else if part.ends_with('?') {
-            let val = u8::from_str_radix(&part[0..1], 16).unwrap() << 4;
-            values.push(val);
-            masks.push(0xF0);
-        } else if part.starts_with('?') {
-            let val = u8::from_str_radix(&part[1..2], 16).unwrap();
-            values.push(val);
-            masks.push(0x0F);
-        } else {
-            let val = u8::from_str_radix(part, 16).unwrap();
-            values.push(val);
-            masks.push(0xFF);
+/// The value, mask and byte class tables of every pattern in an engine,
+/// flattened so verification walks contiguous memory. Patterns with
+/// variable-length jumps are also kept whole for the slow path.
+#[derive(Default)]
+pub struct PatternTable {
+    all_values: Vec<u8>,
+    all_masks: Vec<u8>,
+    all_classes: Vec<(usize, ByteClass)>,
+    gapped: Vec<Pattern>,
+    lookbehind: usize,
+}
+
+impl PatternTable {
+    /// Appends `pattern` to the tables and returns its description.
+    pub fn push(&mut self, id: usize, pattern: &Pattern, anchor_offset: usize) -> PatternInfo {
+        let info = PatternInfo {
+            id,
+            len: pattern.len(),
+            data_offset: self.all_values.len(),
+            anchor_offset,
+            target_offset: pattern.target.unwrap_or(0),
+            class_offset: self.all_classes.len(),
+            class_len: pattern.classes.len(),
+            gapped: (!pattern.gaps.is_empty()).then_some(self.gapped.len()),
+        };
+        self.all_values.extend_from_slice(&pattern.values);
+        self.all_masks.extend_from_slice(&pattern.masks);
+        self.all_classes.extend_from_slice(&pattern.classes);
+        if info.gapped.is_some() {
+            self.gapped.push(pattern.clone());
         }
+
+        // the longest jumps before the anchor put the start furthest back
+        let jumps = pattern
+            .gaps
+            .iter()
+            .filter(|gap| gap.at <= anchor_offset)
+            .fold(0, |jumps: usize, gap| jumps.saturating_add(gap.max));
+        self.lookbehind = self.lookbehind.max(anchor_offset.saturating_add(jumps));
+        info
     }
-    (values, masks)
-}
 
-pub fn find_best_anchor(values: &[u8], masks: &[u8]) -> (Vec<u8>, usize) {
-    let mut best_len = 0;
-    let mut best_start = 0;
-    let mut current_start = 0;
-    let mut current_len = 0;
-    for (i, &mask) in masks.iter().enumerate() {
-        if mask == 0xFF {
-            if current_len == 0 {
-                current_start = i;
+    /// How far before its anchor a match of any pattern can start.
+    pub fn lookbehind(&self) -> usize {
+        self.lookbehind
+    }
+
+    /// Verifies `pat` around an anchor found at `anchor_pos`, pushing its
+    /// matches to `out`.
+    #[inline(always)]
+    pub fn find(&self, data: &[u8], anchor_pos: usize, pat: &PatternInfo, out: &mut Ordered) {
+        if let Some(index) = pat.gapped {
+            return self.find_gapped(data, anchor_pos, pat, &self.gapped[index], out);
+        }
+
+        let Some(start) = anchor_pos.checked_sub(pat.anchor_offset) else {
+            return;
+        };
+        if self.verify(data, start, pat, 0..pat.len) {
+            out.push(MatchedPattern {
+                start,
+                end: start + pat.len,
+                target: start + pat.target_offset,
+                pattern_id: PatternId(pat.id),
+            });
+        }
+    }
+
+    /// Verifies each of `patterns` around an anchor found at `anchor_pos`.
+    #[inline(always)]
+    pub fn find_all(
+        &self,
+        data: &[u8],
+        anchor_pos: usize,
+        patterns: &[PatternInfo],
+        out: &mut Ordered,
+    ) {
+        for pat in patterns {
+            self.find(data, anchor_pos, pat, out);
+        }
+    }
+
+    /// Checks the pattern positions in `range` against `data`, with the first
+    /// of them placed at `start`.
+    #[inline(always)]
+    fn verify(&self, data: &[u8], start: usize, pat: &PatternInfo, range: Range<usize>) -> bool {
+        let len = range.len();
+        let Some(data_slice) = data.get(start..start + len) else {
+            return false;
+        };
+        let pat_vals = &self.all_values[pat.data_offset + range.start..][..len];
+        let pat_masks = &self.all_masks[pat.data_offset + range.start..][..len];
+
+        for i in 0..len {
+            if (data_slice[i] & pat_masks[i]) != pat_vals[i] {
+                return false;
             }
-            current_len += 1;
-        } else {
-            if current_len > best_len {
-                best_len = current_len;
-                best_start = current_start;
+        }
+
+        self.all_classes[pat.class_offset..pat.class_offset + pat.class_len]
+            .iter()
+            .filter(|(i, _)| range.contains(i))
+            .all(|(i, class)| class.contains(data_slice[i - range.start]))
+    }
+
+    /// Slow path for patterns with variable-length jumps.
+    ///
+    /// A start has a single match, found by [`Pattern::resolve_jumps`], so the
+    /// matches cannot depend on which segment holds the anchor. Every start
+    /// the anchor's segment can be reached from is tried, and its match is
+    /// kept when it puts that segment at the anchor.
+    #[cold]
+    fn find_gapped(
+        &self,
+        data: &[u8],
+        anchor_pos: usize,
+        pat: &PatternInfo,
+        pattern: &Pattern,
+        out: &mut Ordered,
+    ) {
+        let segment = pattern
+            .gaps
+            .partition_point(|gap| gap.at <= pat.anchor_offset);
+        let range = pattern.segment_range(segment);
+        let Some(seg_start) = anchor_pos.checked_sub(pat.anchor_offset - range.start) else {
+            return;
+        };
+        if !self.verify(data, seg_start, pat, range.clone()) {
+            return;
+        }
+
+        // the segments before the anchor's span `range.start` positions plus
+        // the bytes skipped by the jumps between them
+        let before = &pattern.gaps[..segment];
+        let min: usize = before.iter().map(|gap| gap.min).sum();
+        let max: usize = before.iter().map(|gap| gap.max).sum();
+        let Some(last) = seg_start.checked_sub(range.start + min) else {
+            return;
+        };
+        let first = seg_start.saturating_sub(range.start + max);
+
+        let mut jumps = Jumps::new(pattern, first..=last, data.len());
+        for start in first..=last {
+            if let Some(end) = pattern.resolve_jumps(data, start, &mut jumps)
+                && jumps.starts()[segment] == seg_start
+            {
+                out.push(gapped_match(pat, pattern, jumps.starts(), end));
             }
-            current_len = 0;
         }
     }
-    if current_len > best_len {
-        best_len = current_len;
-        best_start = current_start;
+}
+
+/// The match of a gapped pattern whose segments start at `starts`.
+fn gapped_match(
+    pat: &PatternInfo,
+    pattern: &Pattern,
+    starts: &[usize],
+    end: usize,
+) -> MatchedPattern {
+    // the target belongs to the last segment starting at or before it
+    let target_segment = pattern
+        .gaps
+        .partition_point(|gap| gap.at <= pat.target_offset);
+    let target_range = pattern.segment_range(target_segment);
+    MatchedPattern {
+        start: starts[0],
+        end,
+        target: starts[target_segment] + (pat.target_offset - target_range.start),
+        pattern_id: PatternId(pat.id),
+    }
+}
+
+/// Delivers matches sorted by start and then by pattern, whatever order the
+/// anchors of their patterns are found in.
+///
+/// Engines [`push`](Self::push) every match and regularly
+/// [`advance`](Self::advance) to the position below which no anchor remains
+/// to be found. Since no match starts more than the table's
+/// [`lookbehind`](PatternTable::lookbehind) before its anchor, every match
+/// starting before `position - lookbehind` can then be delivered.
+pub struct Ordered<'a> {
+    /// Matches not delivered yet, sorted.
+    pending: VecDeque<MatchedPattern>,
+    lookbehind: usize,
+    on_match: &'a mut dyn FnMut(MatchedPattern) -> Scan,
+}
+
+impl<'a> Ordered<'a> {
+    pub fn new(table: &PatternTable, on_match: &'a mut dyn FnMut(MatchedPattern) -> Scan) -> Self {
+        Ordered {
+            pending: VecDeque::new(),
+            lookbehind: table.lookbehind(),
+            on_match,
+        }
+    }
+
+    #[inline(always)]
+    pub fn push(&mut self, matched: MatchedPattern) {
+        match self.pending.back() {
+            Some(last) if order(last) > order(&matched) => self.insert(matched),
+            _ => self.pending.push_back(matched),
+        }
+    }
+
+    /// Inserts a match that arrived out of order, looking for its place from
+    /// the back since it rarely has to move far.
+    #[cold]
+    fn insert(&mut self, matched: MatchedPattern) {
+        let key = order(&matched);
+        let at = self
+            .pending
+            .iter()
+            .rposition(|pending| order(pending) <= key)
+            .map_or(0, |i| i + 1);
+        self.pending.insert(at, matched);
     }
-    if best_len == 0 {
-        return (vec![values[0]], 0);
+
+    /// Records that every anchor still to be found is at `position` or
+    /// later, delivering the matches nothing can precede any more.
+    #[inline(always)]
+    pub fn advance(&mut self, position: usize) -> Scan {
+        if self.pending.is_empty() {
+            return Scan::Continue;
+        }
+        self.deliver(position.saturating_sub(self.lookbehind))
     }
-    (
-        values[best_start..best_start + best_len].to_vec(),
-        best_start,
-    )
+
+    /// Delivers every remaining match once the input is exhausted.
+    pub fn finish(&mut self) {
+        self.deliver(usize::MAX);
+    }
+
+    /// Delivers, in order, the pending matches starting before `bound`.
+    fn deliver(&mut self, bound: usize) -> Scan {
+        while let Some(matched) = self.pending.front()
+            && matched.start < bound
+        {
+            let matched = *matched;
+            self.pending.pop_front();
+            if (self.on_match)(matched) == Scan::Stop {
+                return Scan::Stop;
+            }
+        }
+        Scan::Continue
+    }
+}
+
+/// The order matches are delivered in: by start, then pattern, then end.
+#[inline(always)]
+fn order(matched: &MatchedPattern) -> (usize, PatternId, usize) {
+    (matched.start, matched.pattern_id, matched.end)
+}
+
+/// The literals an engine searches for to find candidate positions of a
+/// pattern, all of them starting [`offset`](Self::offset) positions into it.
+///
+/// A pattern without literals cannot be anchored and is verified at every
+/// offset of the input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnchorSet {
+    pub(crate) literals: Vec<Vec<u8>>,
+    pub(crate) offset: usize,
+}
+
+impl AnchorSet {
+    pub fn literals(&self) -> &[Vec<u8>] {
+        &self.literals
+    }
+
+    /// Position of the anchor within the pattern.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Number of positions the anchor covers.
+    pub fn len(&self) -> usize {
+        self.literals.first().map_or(0, Vec::len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_anchored(&self) -> bool {
+        !self.literals.is_empty()
+    }
+}
+
+/// Upper bound on the number of literals a single anchor expands to.
+const MAX_ANCHOR_LITERALS: usize = 16;
+
+/// Number of leading anchor bytes Teddy fingerprints.
+pub(crate) const PREFILTER_LEN: usize = 3;
+
+/// Picks the rarest run of positions accepting at most two bytes each
+/// (fixed bytes, case-insensitive letters, two-way alternations) that
+/// expands to no more than [`MAX_ANCHOR_LITERALS`] literals. Wider
+/// positions, such as wildcards and negations, are never part of such an
+/// anchor.
+///
+/// Patterns without any such position are anchored on their rarest masked
+/// positions instead, such as a nibble (`4?`) expanding to 16 literals.
+/// When even that is impossible the anchor is empty.
+pub fn find_best_anchor(pattern: &Pattern, frequencies: &ByteFrequencies) -> AnchorSet {
+    let classes: Vec<ByteClass> = (0..pattern.len())
+        .map(|i| pattern.position_class(i))
+        .collect();
+
+    let run = best_run(pattern, &classes, frequencies, 2)
+        .or_else(|| best_run(pattern, &classes, frequencies, MAX_ANCHOR_LITERALS));
+    let Some((start, len)) = run else {
+        return AnchorSet {
+            literals: Vec::new(),
+            offset: 0,
+        };
+    };
+
+    let mut literals = vec![Vec::with_capacity(len)];
+    for class in &classes[start..start + len] {
+        literals = literals
+            .into_iter()
+            .flat_map(|prefix| {
+                class.iter().map(move |b| {
+                    let mut literal = prefix.clone();
+                    literal.push(b);
+                    literal
+                })
+            })
+            .collect();
+    }
+
+    AnchorSet {
+        literals,
+        offset: start,
+    }
+}
+
+/// The rarest run of positions accepting at most `widest` bytes each, as
+/// `(start, len)`.
+///
+/// Every start is extended as far right as the literal budget allows. Since
+/// Teddy only fingerprints the first [`PREFILTER_LEN`] bytes of an anchor,
+/// runs are ranked by how likely those are, then by the whole run.
+fn best_run(
+    pattern: &Pattern,
+    classes: &[ByteClass],
+    frequencies: &ByteFrequencies,
+    widest: usize,
+) -> Option<(usize, usize)> {
+    // log-probabilities, so long runs do not underflow
+    let rarity: Vec<f64> = classes
+        .iter()
+        .map(|class| {
+            class
+                .iter()
+                .map(|b| frequencies.probability(b))
+                .sum::<f64>()
+                .ln()
+        })
+        .collect();
+
+    // (prefix rarity, run rarity, start, len)
+    let mut best: Option<(f64, f64, usize, usize)> = None;
+    for start in 0..classes.len() {
+        let mut len = 0;
+        let mut count = 1;
+        while let Some(class) = classes.get(start + len) {
+            // a run cannot span a variable-length jump
+            let jump = len > 0 && pattern.gaps.iter().any(|gap| gap.at == start + len);
+            if jump || class.len() > widest || count * class.len() > MAX_ANCHOR_LITERALS {
+                break;
+            }
+            count *= class.len();
+            len += 1;
+        }
+        if len == 0 {
+            continue;
+        }
+
+        let prefix = rarity[start..start + len.min(PREFILTER_LEN)].iter().sum();
+        let run = rarity[start..start + len].iter().sum();
+        if best.is_none_or(|(best_prefix, best_run, ..)| {
+            prefix < best_prefix || (prefix == best_prefix && run < best_run)
+        }) {
+            best = Some((prefix, run, start, len));
+        }
+    }
+
+    best.map(|(.., start, len)| (start, len))
 }
@@ -0,0 +1,65 @@
+use crate::{
+    engine::{
+        EngineKind, LookupEngine, MatchedPattern, Scan,
+        common::{AnchorSet, Ordered, PatternInfo, PatternTable},
+    },
+    pattern::Pattern,
+};
+
+pub struct Scalar {
+    /// Anchor literals and their patterns, indexed by the first literal byte.
+    by_first_byte: Vec<Vec<(Vec<u8>, PatternInfo)>>,
+    unanchored: Vec<PatternInfo>,
+    table: PatternTable,
+}
+
+impl LookupEngine for Scalar {
+    fn new(patterns: &[Pattern], anchors: &[AnchorSet]) -> Self
+    where
+        Self: Sized,
+    {
+        let mut table = PatternTable::default();
+        let mut by_first_byte = vec![Vec::new(); 256];
+        let mut unanchored = Vec::new();
+
+        for (id, pattern) in patterns.iter().enumerate() {
+            let anchor = &anchors[id];
+            let pat = table.push(id, pattern, anchor.offset);
+            if !anchor.is_anchored() {
+                unanchored.push(pat);
+            }
+
+            for literal in &anchor.literals {
+                by_first_byte[literal[0] as usize].push((literal.clone(), pat));
+            }
+        }
+
+        Scalar {
+            by_first_byte,
+            unanchored,
+            table,
+        }
+    }
+
+    fn scan(&self, data: &[u8], on_match: &mut dyn FnMut(MatchedPattern) -> Scan) {
+        let mut out = Ordered::new(&self.table, on_match);
+
+        for (pos, &byte) in data.iter().enumerate() {
+            if out.advance(pos) == Scan::Stop {
+                return;
+            }
+            for (literal, pat) in &self.by_first_byte[byte as usize] {
+                if data[pos..].starts_with(literal) {
+                    self.table.find(data, pos, pat, &mut out);
+                }
+            }
+            self.table.find_all(data, pos, &self.unanchored, &mut out);
+        }
+
+        out.finish();
+    }
+
+    fn kind(&self) -> EngineKind {
+        EngineKind::Scalar
+    }
+}
@@ -7,397 +7,674 @@ use std::arch::aarch64::*;
 #[cfg(target_arch = "arm")]
 use std::arch::arm::*;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use crate::{
     engine::{
-        LookupEngine, MatchedPattern, Scan,
-        common::{self, PatternInfo},
+        EngineKind, LookupEngine, MatchedPattern, Scan, SimdPath,
+        common::{AnchorSet, Ordered, PREFILTER_LEN, PatternInfo, PatternTable},
     },
-    pattern::PatternId,
+    pattern::Pattern,
 };
 
+/// Most buckets a single pass can test, one bit each in two byte-wide halves.
+const MAX_BUCKETS: usize = 16;
+
+/// Most groups [`EngineKind::Auto`] builds Teddy with. Each group is another
+/// set of lookups per block, and past about this many keys the Anchor
+/// engine's automaton scans faster.
+pub(crate) const MAX_GROUPS: usize = 24;
+
+/// Keys sharing a bucket before another group is started. Each key widens the
+/// nibble sets of its bucket, so crowded buckets accept almost any input.
+const BUCKET_KEYS: usize = 4;
+
+/// Teddy: the first [`PREFILTER_LEN`] bytes of every anchor (its key) are
+/// spread over groups of up to 16 buckets, and each input byte is looked up
+/// by nibble in per-position tables holding one bit per bucket. ANDing the
+/// lookups of consecutive bytes leaves the buckets that may hold a key
+/// starting there, for every bucket of a group at once.
+///
+/// The input is read once: each block is tested against every group before
+/// moving on, so candidates are verified in ascending order of where their
+/// anchor starts. Matches may still start before earlier ones when their
+/// anchor is not at offset 0; [`Ordered`] puts them back in start order.
 pub struct Teddy {
-    buckets: Vec<Bucket>,
-    all_values: Vec<u8>,
-    all_masks: Vec<u8>,
+    groups: Vec<Group>,
+    unanchored: Vec<PatternInfo>,
+    table: PatternTable,
+    simd: SimdPath,
 }
 
 impl LookupEngine for Teddy {
-    fn new<'s, I>(patterns: I) -> Self
+    fn new(patterns: &[Pattern], anchors: &[AnchorSet]) -> Self
     where
         Self: Sized,
-        I: IntoIterator<Item = &'s str>,
     {
-        let mut all_values = Vec::new();
-        let mut all_masks = Vec::new();
-        let mut groups: HashMap<Vec<u8>, Vec<PatternInfo>> = HashMap::new();
-
-        for (id, pat_str) in patterns.into_iter().enumerate() {
-            let (values, masks) = common::parse_hex_pattern(pat_str);
-            let (anchor, anchor_off) = common::find_best_anchor(&values, &masks);
-
-            let key_len = anchor.len().min(3);
-            let key = anchor[0..key_len].to_vec();
-
-            let data_offset = all_values.len();
-            let len = values.len();
-            all_values.extend_from_slice(&values);
-            all_masks.extend_from_slice(&masks);
-
-            groups.entry(key).or_default().push(PatternInfo {
-                id,
-                len,
-                data_offset,
-                anchor_offset: anchor_off,
-            });
-        }
+        let mut table = PatternTable::default();
+        let mut groups: BTreeMap<Vec<u8>, Vec<PatternInfo>> = BTreeMap::new();
+        let mut unanchored = Vec::new();
+
+        for (id, pattern) in patterns.iter().enumerate() {
+            let anchor = &anchors[id];
+            let pat = table.push(id, pattern, anchor.offset);
+            if !anchor.is_anchored() {
+                unanchored.push(pat);
+            }
 
-        let mut buckets = Vec::new();
-        for (key, patterns) in groups {
-            buckets.push(Teddy::build_bucket(key, patterns));
+            let mut keys: Vec<Vec<u8>> = anchor
+                .literals
+                .iter()
+                .map(|literal| literal[0..literal.len().min(PREFILTER_LEN)].to_vec())
+                .collect();
+            keys.sort_unstable();
+            keys.dedup();
+
+            for key in keys {
+                groups.entry(key).or_default().push(pat);
+            }
         }
 
         Teddy {
-            buckets,
-            all_values,
-            all_masks,
+            groups: Teddy::build_groups(groups.into_iter().collect()),
+            unanchored,
+            table,
+            simd: SimdPath::detect(),
         }
     }
 
     fn scan(&self, data: &[u8], on_match: &mut dyn FnMut(MatchedPattern) -> Scan) {
-        #[cfg(target_arch = "x86_64")]
-        {
-            if is_x86_feature_detected!("avx2") {
-                unsafe {
-                    self.scan_avx2(data, on_match);
-                    return;
-                }
-            }
-        }
-
-        #[cfg(target_arch = "aarch64")]
-        unsafe {
-            self.scan_neon(data, on_match);
+        if self.groups.is_empty() && self.unanchored.is_empty() {
             return;
         }
 
-        #[cfg(target_arch = "arm")]
-        unsafe {
-            self.scan_neon_arm32(data, &mut on_match);
-            return;
+        let mut out = Ordered::new(&self.table, on_match);
+        // `simd` only names a path the CPU was detected to support
+        let scan = match self.simd {
+            #[cfg(target_arch = "x86_64")]
+            SimdPath::Avx2 => unsafe { self.scan_avx2(data, &mut out) },
+
+            #[cfg(target_arch = "aarch64")]
+            SimdPath::Neon => unsafe { self.scan_neon(data, &mut out) },
+
+            #[cfg(target_arch = "arm")]
+            SimdPath::Neon => unsafe { self.scan_neon_arm32(data, &mut out) },
+
+            _ => self.scan_slow(data, &mut out),
+        };
+        if scan == Scan::Continue {
+            out.finish();
         }
+    }
 
-        #[allow(unreachable_code)]
-        self.scan_slow(data, 0, on_match);
+    fn kind(&self) -> EngineKind {
+        EngineKind::Teddy
+    }
+
+    fn simd(&self) -> SimdPath {
+        self.simd
     }
 }
 
 impl Teddy {
-    #[cfg(target_arch = "x86_64")]
-    fn build_bucket(key: Vec<u8>, patterns: Vec<PatternInfo>) -> Bucket {
-        unsafe {
-            let f0 = Some(_mm256_set1_epi8(key[0] as i8));
-            let f1 = (key.len() >= 2).then(|| _mm256_set1_epi8(key[1] as i8));
-            let f2 = (key.len() >= 3).then(|| _mm256_set1_epi8(key[2] as i8));
-
-            Bucket {
-                fingerprint_avx: [f0, f1, f2],
-                fingerprint_bytes: key,
-                patterns,
-            }
-        }
+    /// Whether the keys of `anchors` fit in [`MAX_GROUPS`] groups.
+    pub(crate) fn fits(anchors: &[AnchorSet]) -> bool {
+        let keys: BTreeSet<&[u8]> = anchors
+            .iter()
+            .flat_map(|anchor| &anchor.literals)
+            .map(|literal| &literal[0..literal.len().min(PREFILTER_LEN)])
+            .collect();
+        keys.len() <= MAX_GROUPS * MAX_BUCKETS * BUCKET_KEYS
     }
 
-    #[cfg(target_arch = "aarch64")]
-    fn build_bucket(key: Vec<u8>, patterns: Vec<PatternInfo>) -> Bucket {
-        unsafe {
-            let f0 = Some(vdupq_n_u8(key[0]));
-            let f1 = if key.len() >= 2 {
-                Some(vdupq_n_u8(key[1]))
-            } else {
-                None
-            };
-            let f2 = if key.len() >= 3 {
-                Some(vdupq_n_u8(key[2]))
-            } else {
-                None
-            };
-
-            Bucket {
-                fingerprint_neon: [f0, f1, f2],
-                fingerprint_bytes: key,
-                patterns,
-            }
+    /// Spreads the keys over as few groups as keep [`BUCKET_KEYS`] keys per
+    /// bucket, each group using one bucket per key up to [`MAX_BUCKETS`].
+    ///
+    /// Keys are sorted by length and then by value and split into contiguous
+    /// runs. Neighbouring keys tend to share leading nibbles, and short keys,
+    /// which accept any byte past their end, end up together instead of
+    /// diluting the buckets of long ones.
+    fn build_groups(mut keys: Vec<(Vec<u8>, Vec<PatternInfo>)>) -> Vec<Group> {
+        keys.sort_by(|(a, _), (b, _)| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+
+        let count = keys.len().div_ceil(MAX_BUCKETS * BUCKET_KEYS);
+        let mut groups = Vec::with_capacity(count);
+        let mut keys = keys.into_iter();
+        for index in 0..count {
+            let total = keys.len() / (count - index);
+            groups.push(Group::new(keys.by_ref().take(total).collect()));
         }
+        groups
     }
 
     #[cfg(target_arch = "x86_64")]
     #[target_feature(enable = "avx2")]
-    unsafe fn scan_avx2<F>(&self, data: &[u8], on_match: &mut F)
-    where
-        F: FnMut(MatchedPattern) -> Scan + ?Sized,
-    {
-        let len = data.len();
-        // limit: 32 bytes (vector) + 2 bytes
-        if len < 34 {
-            self.scan_slow(data, 0, on_match);
-            return;
-        }
+    unsafe fn scan_avx2(&self, data: &[u8], out: &mut Ordered) -> Scan {
+        unsafe { self.scan_blocks::<Avx2Tables, 32>(data, out) }
+    }
 
-        // align the limit to 32-byte boundaries to keep SIMD and scalar synchronized
-        // we stop SIMD 34 bytes before end to be safe.
-        let safe_limit = len - 34;
-        let aligned_limit = safe_limit & !0x1F; // floor to multiple of 32
-
-        for bucket in &self.buckets {
-            let fp = bucket.fingerprint_avx;
-            let mut i = 0;
-            while i <= aligned_limit {
-                unsafe {
-                    let ptr = data.as_ptr().add(i);
-
-                    let block_0 = _mm256_loadu_si256(ptr as *const _);
-                    let mut candidates = _mm256_cmpeq_epi8(block_0, fp[0].unwrap());
-
-                    if let Some(reg_b1) = fp[1] {
-                        let block_1 = _mm256_loadu_si256(ptr.add(1) as *const _);
-                        let cmp = _mm256_cmpeq_epi8(block_1, reg_b1);
-                        candidates = _mm256_and_si256(candidates, cmp);
-                    }
-
-                    if let Some(reg_b2) = fp[2] {
-                        let block_2 = _mm256_loadu_si256(ptr.add(2) as *const _);
-                        let cmp = _mm256_cmpeq_epi8(block_2, reg_b2);
-                        candidates = _mm256_and_si256(candidates, cmp);
-                    }
-
-                    let mask = _mm256_movemask_epi8(candidates) as u32;
-
-                    if mask != 0 {
-                        let mut bits = mask;
-                        while bits != 0 {
-                            let bit_idx = bits.trailing_zeros() as usize;
-                            let match_pos = i + bit_idx;
-                            if !self.verify_bucket_patterns(data, match_pos, bucket, on_match) {
-                                return;
-                            }
-                            bits &= bits - 1;
-                        }
-                    }
-                    i += 32;
-                }
-            }
-        }
+    #[cfg(target_arch = "aarch64")]
+    unsafe fn scan_neon(&self, data: &[u8], out: &mut Ordered) -> Scan {
+        unsafe { self.scan_blocks::<NeonTables, 16>(data, out) }
+    }
 
-        self.scan_slow(data, aligned_limit + 32, on_match);
+    #[cfg(target_arch = "arm")]
+    #[target_feature(enable = "neon")]
+    unsafe fn scan_neon_arm32(&self, data: &[u8], out: &mut Ordered) -> Scan {
+        unsafe { self.scan_blocks::<Arm32Tables, 16>(data, out) }
     }
 
-    #[cfg(any(target_arch = "aarch64"))]
-    unsafe fn scan_neon<F>(&self, data: &[u8], on_match: &mut F)
-    where
-        F: FnMut(MatchedPattern) -> Scan + ?Sized,
-    {
-        let len = data.len();
-        // limit: 16 bytes (vector) + 2 bytes
-        if len < 18 {
-            self.scan_slow(data, 0, on_match);
-            return;
+    /// Tests each block of `N` positions against every group before moving
+    /// on to the next. Always inlined, so the table lookups are compiled with
+    /// the target features of the entry point calling it.
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64", target_arch = "arm"))]
+    #[inline(always)]
+    unsafe fn scan_blocks<T: BlockTables<N>, const N: usize>(
+        &self,
+        data: &[u8],
+        out: &mut Ordered,
+    ) -> Scan {
+        let read = N + PREFILTER_LEN - 1;
+
+        let mut tables: Vec<T> = Vec::with_capacity(self.groups.len());
+        for group in &self.groups {
+            tables.push(unsafe { T::new(group) });
         }
+        let mut hits = vec![BlockHits::EMPTY; self.groups.len()];
 
-        // align to 16-byte boundary
-        let safe_limit = len - 18;
-        let aligned_limit = safe_limit & !0xF;
-
-        for bucket in &self.buckets {
-            let fp = bucket.fingerprint_neon;
-            let mut i = 0;
-            while i <= aligned_limit {
-                unsafe {
-                    let ptr = data.as_ptr().add(i);
-
-                    let block_0 = vld1q_u8(ptr);
-                    let mut candidates = vceqq_u8(block_0, fp[0].unwrap());
-
-                    if let Some(reg_b1) = fp[1] {
-                        let block_1 = vld1q_u8(ptr.add(1));
-                        let cmp = vceqq_u8(block_1, reg_b1);
-                        candidates = vandq_u8(candidates, cmp);
-                    }
-
-                    if let Some(reg_b2) = fp[2] {
-                        let block_2 = vld1q_u8(ptr.add(2));
-                        let cmp = vceqq_u8(block_2, reg_b2);
-                        candidates = vandq_u8(candidates, cmp);
-                    }
-
-                    if vmaxvq_u8(candidates) != 0 {
-                        let mut res_arr = [0u8; 16];
-                        vst1q_u8(res_arr.as_mut_ptr(), candidates);
-                        for k in 0..16 {
-                            if res_arr[k] == 0xFF {
-                                let match_pos = i + k;
-                                if !self.verify_bucket_patterns(data, match_pos, bucket, on_match) {
-                                    return;
-                                }
-                            }
-                        }
-                    }
-                    i += 16;
-                }
+        let mut i = 0;
+        while i + read <= data.len() {
+            if out.advance(i) == Scan::Stop {
+                return Scan::Stop;
+            }
+
+            let ptr = unsafe { data.as_ptr().add(i) };
+            let mut found = 0;
+            for (tables, hits) in tables.iter().zip(&mut hits) {
+                unsafe { tables.block(ptr, hits) };
+                found |= hits.positions;
             }
+            if found != 0 || !self.unanchored.is_empty() {
+                self.verify_block(data, i, &hits, out);
+            }
+            i += N;
         }
 
-        self.scan_slow(data, aligned_limit + 16, on_match);
+        // the last blocks are copied out so they can be read in full,
+        // positions past the end are masked off and keys are compared
+        // against `data`, so whatever follows the copy is never matched
+        let mut padded = [0u8; MAX_READ];
+        while i < data.len() {
+            if out.advance(i) == Scan::Stop {
+                return Scan::Stop;
+            }
+
+            let rest = data.len() - i;
+            padded[..rest].copy_from_slice(&data[i..]);
+            for (tables, hits) in tables.iter().zip(&mut hits) {
+                unsafe { tables.block(padded.as_ptr(), hits) };
+                hits.positions &= u32::MAX >> 32usize.saturating_sub(rest);
+            }
+            self.verify_block(data, i, &hits, out);
+            i += N;
+        }
+        Scan::Continue
     }
 
-    #[cfg(target_arch = "arm")]
-    #[target_feature(enable = "neon")]
-    unsafe fn scan_neon_arm32(
+    /// Verifies the candidates every group found in the block of `N`
+    /// positions starting at `start`, and the patterns without an anchor at
+    /// each of them.
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64", target_arch = "arm"))]
+    #[inline(always)]
+    fn verify_block<const N: usize>(
         &self,
         data: &[u8],
-        on_match: &mut impl FnMut((usize, usize)) -> bool,
+        start: usize,
+        hits: &[BlockHits<N>],
+        out: &mut Ordered,
     ) {
-        let len = data.len();
-        if len < 18 {
-            self.scan_slow(data, 0, on_match);
-            return;
+        let mut positions = hits.iter().fold(0, |positions, h| positions | h.positions);
+        while positions != 0 {
+            let j = positions.trailing_zeros() as usize;
+            positions &= positions - 1;
+
+            for (group, hits) in self.groups.iter().zip(hits) {
+                if hits.positions & (1 << j) != 0 {
+                    group.verify(&self.table, data, start + j, hits.buckets(j), out);
+                }
+            }
         }
 
-        let safe_limit = len - 18;
-        let aligned_limit = safe_limit & !0xF;
-
-        for bucket in &self.buckets {
-            let fp = bucket.fingerprint_neon;
-            let mut i = 0;
-            while i <= aligned_limit {
-                unsafe {
-                    let ptr = data.as_ptr().add(i);
-
-                    let block_0 = vld1q_u8(ptr);
-                    let mut candidates = vceqq_u8(block_0, fp[0].unwrap());
-
-                    if let Some(reg_b1) = fp[1] {
-                        let block_1 = vld1q_u8(ptr.add(1));
-                        let cmp = vceqq_u8(block_1, reg_b1);
-                        candidates = vandq_u8(candidates, cmp);
-                    }
-
-                    if let Some(reg_b2) = fp[2] {
-                        let block_2 = vld1q_u8(ptr.add(2));
-                        let cmp = vceqq_u8(block_2, reg_b2);
-                        candidates = vandq_u8(candidates, cmp);
-                    }
-
-                    if self.neon_has_match_arm32(candidates) {
-                        let mut res_arr = [0u8; 16];
-                        vst1q_u8(res_arr.as_mut_ptr(), candidates);
-                        for k in 0..16 {
-                            if res_arr[k] == 0xFF {
-                                let match_pos = i + k;
-                                if !self.verify_bucket_patterns(data, match_pos, bucket, on_match) {
-                                    return;
-                                }
-                            }
-                        }
-                    }
-                    i += 16;
+        if !self.unanchored.is_empty() {
+            for pos in start..data.len().min(start + N) {
+                self.table.find_all(data, pos, &self.unanchored, out);
+            }
+        }
+    }
+
+    /// Looks every byte up in the nibble tables one at a time, for CPUs
+    /// without a SIMD path.
+    fn scan_slow(&self, data: &[u8], out: &mut Ordered) -> Scan {
+        for pos in 0..data.len() {
+            if out.advance(pos) == Scan::Stop {
+                return Scan::Stop;
+            }
+
+            for group in &self.groups {
+                // past the end of `data` no bucket is ruled out, the keys are
+                // compared in full before verifying anyway
+                let hits = data[pos..]
+                    .iter()
+                    .take(PREFILTER_LEN)
+                    .enumerate()
+                    .fold(u16::MAX, |hits, (k, &byte)| {
+                        hits & group.masks.buckets(k, byte)
+                    });
+
+                if hits != 0 {
+                    group.verify(&self.table, data, pos, hits, out);
                 }
             }
+            self.table.find_all(data, pos, &self.unanchored, out);
         }
-        self.scan_slow(data, aligned_limit + 16, on_match);
+        Scan::Continue
     }
+}
 
-    #[cfg(target_arch = "arm")]
+/// Bytes read by the widest block, 32 positions for AVX2.
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64", target_arch = "arm"))]
+const MAX_READ: usize = 32 + PREFILTER_LEN - 1;
+
+/// The nibble masks of a group loaded into vector registers, finding the
+/// buckets of `N` positions at once.
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64", target_arch = "arm"))]
+trait BlockTables<const N: usize>: Sized {
+    /// Loads the masks of `group`. The CPU must support the instructions
+    /// the tables are implemented with.
+    unsafe fn new(group: &Group) -> Self;
+
+    /// Finds the buckets of the `N` positions starting at `ptr`, which must
+    /// be readable for `N + PREFILTER_LEN - 1` bytes.
+    unsafe fn block(&self, ptr: *const u8, hits: &mut BlockHits<N>);
+}
+
+/// The buckets a group found at each position of a block.
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64", target_arch = "arm"))]
+#[derive(Clone, Copy)]
+struct BlockHits<const N: usize> {
+    /// Bit `j` is set when some bucket may hold a key starting at `j`.
+    positions: u32,
+    low: [u8; N],
+    high: [u8; N],
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64", target_arch = "arm"))]
+impl<const N: usize> BlockHits<N> {
+    const EMPTY: Self = BlockHits {
+        positions: 0,
+        low: [0; N],
+        high: [0; N],
+    };
+
+    /// The buckets found at position `j`, as in [`NibbleMasks::buckets`].
     #[inline(always)]
-    unsafe fn neon_has_match_arm32(&self, v: uint8x16_t) -> bool {
-        unsafe {
-            let u32s: uint32x4_t = std::mem::transmute(v);
-            let t1 = vgetq_lane_u32(u32s, 0);
-            let t2 = vgetq_lane_u32(u32s, 1);
-            let t3 = vgetq_lane_u32(u32s, 2);
-            let t4 = vgetq_lane_u32(u32s, 3);
-            (t1 | t2 | t3 | t4) != 0
+    fn buckets(&self, j: usize) -> u16 {
+        self.low[j] as u16 | (self.high[j] as u16) << 8
+    }
+
+    /// Sets `positions` from the stored bucket bytes.
+    #[cfg(any(target_arch = "aarch64", target_arch = "arm"))]
+    #[inline(always)]
+    fn mark_positions(&mut self) {
+        self.positions = (0..N)
+            .filter(|&j| self.low[j] | self.high[j] != 0)
+            .fold(0, |positions, j| positions | 1 << j);
+    }
+}
+
+/// The nibble masks of a group loaded into AVX2 registers.
+#[cfg(target_arch = "x86_64")]
+struct Avx2Tables {
+    lo: [[__m256i; PREFILTER_LEN]; 2],
+    hi: [[__m256i; PREFILTER_LEN]; 2],
+    wide: bool,
+}
+
+#[cfg(target_arch = "x86_64")]
+impl BlockTables<32> for Avx2Tables {
+    /// Repeats every table in both 128-bit lanes, since `vpshufb` looks up
+    /// within a lane.
+    #[target_feature(enable = "avx2")]
+    unsafe fn new(group: &Group) -> Self {
+        let load = |tables: &[[[u8; 16]; PREFILTER_LEN]; 2]| {
+            let mut regs = [[_mm256_setzero_si256(); PREFILTER_LEN]; 2];
+            for (half, tables) in regs.iter_mut().zip(tables) {
+                for (reg, table) in half.iter_mut().zip(tables) {
+                    *reg = unsafe {
+                        _mm256_broadcastsi128_si256(_mm_loadu_si128(table.as_ptr() as *const _))
+                    };
+                }
+            }
+            regs
+        };
+
+        Avx2Tables {
+            lo: load(&group.masks.lo),
+            hi: load(&group.masks.hi),
+            wide: group.is_wide(),
         }
     }
 
-    fn scan_slow<F>(&self, data: &[u8], start_offset: usize, on_match: &mut F)
-    where
-        F: FnMut(MatchedPattern) -> Scan + ?Sized,
-    {
-        let len = data.len();
-        if start_offset >= len {
-            return;
+    #[target_feature(enable = "avx2")]
+    #[inline]
+    unsafe fn block(&self, ptr: *const u8, hits: &mut BlockHits<32>) {
+        unsafe {
+            let nibble = _mm256_set1_epi8(0x0F);
+            let zero = _mm256_setzero_si256();
+            let mut low_buckets = _mm256_set1_epi8(-1);
+            let mut high_buckets = zero;
+            if self.wide {
+                high_buckets = _mm256_set1_epi8(-1);
+            }
+
+            for k in 0..PREFILTER_LEN {
+                let block = _mm256_loadu_si256(ptr.add(k) as *const _);
+                let lo_nibbles = _mm256_and_si256(block, nibble);
+                let hi_nibbles = _mm256_and_si256(_mm256_srli_epi16(block, 4), nibble);
+
+                low_buckets = _mm256_and_si256(
+                    low_buckets,
+                    _mm256_and_si256(
+                        _mm256_shuffle_epi8(self.lo[0][k], lo_nibbles),
+                        _mm256_shuffle_epi8(self.hi[0][k], hi_nibbles),
+                    ),
+                );
+                if self.wide {
+                    high_buckets = _mm256_and_si256(
+                        high_buckets,
+                        _mm256_and_si256(
+                            _mm256_shuffle_epi8(self.lo[1][k], lo_nibbles),
+                            _mm256_shuffle_epi8(self.hi[1][k], hi_nibbles),
+                        ),
+                    );
+                }
+            }
+
+            let any = _mm256_or_si256(low_buckets, high_buckets);
+            hits.positions = !(_mm256_movemask_epi8(_mm256_cmpeq_epi8(any, zero)) as u32);
+            if hits.positions != 0 {
+                _mm256_storeu_si256(hits.low.as_mut_ptr() as *mut _, low_buckets);
+                _mm256_storeu_si256(hits.high.as_mut_ptr() as *mut _, high_buckets);
+            }
         }
+    }
+}
+
+/// The nibble masks of a group loaded into NEON registers.
+#[cfg(target_arch = "aarch64")]
+struct NeonTables {
+    lo: [[uint8x16_t; PREFILTER_LEN]; 2],
+    hi: [[uint8x16_t; PREFILTER_LEN]; 2],
+    wide: bool,
+}
 
-        for i in start_offset..len {
-            for bucket in &self.buckets {
-                let fp = &bucket.fingerprint_bytes;
-                if i + fp.len() > len {
-                    continue;
+#[cfg(target_arch = "aarch64")]
+impl BlockTables<16> for NeonTables {
+    unsafe fn new(group: &Group) -> Self {
+        let load = |tables: &[[[u8; 16]; PREFILTER_LEN]; 2]| {
+            let mut regs = unsafe { [[vdupq_n_u8(0); PREFILTER_LEN]; 2] };
+            for (half, tables) in regs.iter_mut().zip(tables) {
+                for (reg, table) in half.iter_mut().zip(tables) {
+                    *reg = unsafe { vld1q_u8(table.as_ptr()) };
                 }
+            }
+            regs
+        };
 
-                if &data[i..i + fp.len()] == fp.as_slice() {
-                    if !self.verify_bucket_patterns(data, i, bucket, on_match) {
-                        return;
-                    }
+        NeonTables {
+            lo: load(&group.masks.lo),
+            hi: load(&group.masks.hi),
+            wide: group.is_wide(),
+        }
+    }
+
+    #[inline]
+    unsafe fn block(&self, ptr: *const u8, hits: &mut BlockHits<16>) {
+        unsafe {
+            let nibble = vdupq_n_u8(0x0F);
+            let mut low_buckets = vdupq_n_u8(0xFF);
+            let mut high_buckets = vdupq_n_u8(0);
+            if self.wide {
+                high_buckets = vdupq_n_u8(0xFF);
+            }
+
+            for k in 0..PREFILTER_LEN {
+                let block = vld1q_u8(ptr.add(k));
+                let lo_nibbles = vandq_u8(block, nibble);
+                let hi_nibbles = vshrq_n_u8(block, 4);
+
+                low_buckets = vandq_u8(
+                    low_buckets,
+                    vandq_u8(
+                        vqtbl1q_u8(self.lo[0][k], lo_nibbles),
+                        vqtbl1q_u8(self.hi[0][k], hi_nibbles),
+                    ),
+                );
+                if self.wide {
+                    high_buckets = vandq_u8(
+                        high_buckets,
+                        vandq_u8(
+                            vqtbl1q_u8(self.lo[1][k], lo_nibbles),
+                            vqtbl1q_u8(self.hi[1][k], hi_nibbles),
+                        ),
+                    );
                 }
             }
+
+            hits.positions = 0;
+            if vmaxvq_u8(vorrq_u8(low_buckets, high_buckets)) != 0 {
+                vst1q_u8(hits.low.as_mut_ptr(), low_buckets);
+                vst1q_u8(hits.high.as_mut_ptr(), high_buckets);
+                hits.mark_positions();
+            }
         }
     }
+}
 
-    #[inline(always)]
-    fn verify_bucket_patterns<F>(
-        &self,
-        data: &[u8],
-        anchor_pos: usize,
-        bucket: &Bucket,
-        on_match: &mut F,
-    ) -> bool
-    where
-        F: FnMut(MatchedPattern) -> Scan + ?Sized,
-    {
-        for pat in &bucket.patterns {
-            if anchor_pos < pat.anchor_offset {
-                continue;
+/// The nibble masks of a group loaded into NEON registers. ARMv7 has no
+/// 16-byte table lookup, so each table is split into two 8-byte halves for
+/// [`arm32_lookup`].
+#[cfg(target_arch = "arm")]
+struct Arm32Tables {
+    lo: [[uint8x8x2_t; PREFILTER_LEN]; 2],
+    hi: [[uint8x8x2_t; PREFILTER_LEN]; 2],
+    wide: bool,
+}
+
+#[cfg(target_arch = "arm")]
+impl BlockTables<16> for Arm32Tables {
+    #[target_feature(enable = "neon")]
+    unsafe fn new(group: &Group) -> Self {
+        let load = |tables: &[[[u8; 16]; PREFILTER_LEN]; 2]| {
+            let empty = unsafe { uint8x8x2_t(vdup_n_u8(0), vdup_n_u8(0)) };
+            let mut regs = [[empty; PREFILTER_LEN]; 2];
+            for (half, tables) in regs.iter_mut().zip(tables) {
+                for (reg, table) in half.iter_mut().zip(tables) {
+                    let table = unsafe { vld1q_u8(table.as_ptr()) };
+                    *reg = unsafe { uint8x8x2_t(vget_low_u8(table), vget_high_u8(table)) };
+                }
             }
-            let start = anchor_pos - pat.anchor_offset;
-            let end = start + pat.len;
-            if end > data.len() {
-                continue;
+            regs
+        };
+
+        Arm32Tables {
+            lo: load(&group.masks.lo),
+            hi: load(&group.masks.hi),
+            wide: group.is_wide(),
+        }
+    }
+
+    #[target_feature(enable = "neon")]
+    #[inline]
+    unsafe fn block(&self, ptr: *const u8, hits: &mut BlockHits<16>) {
+        unsafe {
+            let nibble = vdupq_n_u8(0x0F);
+            let mut low_buckets = vdupq_n_u8(0xFF);
+            let mut high_buckets = vdupq_n_u8(0);
+            if self.wide {
+                high_buckets = vdupq_n_u8(0xFF);
             }
 
-            let mut is_match = true;
-            for k in 0..pat.len {
-                let val = self.all_values[pat.data_offset + k];
-                let mask = self.all_masks[pat.data_offset + k];
-                if (data[start + k] & mask) != val {
-                    is_match = false;
-                    break;
+            for k in 0..PREFILTER_LEN {
+                let block = vld1q_u8(ptr.add(k));
+                let lo_nibbles = vandq_u8(block, nibble);
+                let hi_nibbles = vshrq_n_u8(block, 4);
+
+                low_buckets = vandq_u8(
+                    low_buckets,
+                    vandq_u8(
+                        arm32_lookup(self.lo[0][k], lo_nibbles),
+                        arm32_lookup(self.hi[0][k], hi_nibbles),
+                    ),
+                );
+                if self.wide {
+                    high_buckets = vandq_u8(
+                        high_buckets,
+                        vandq_u8(
+                            arm32_lookup(self.lo[1][k], lo_nibbles),
+                            arm32_lookup(self.hi[1][k], hi_nibbles),
+                        ),
+                    );
                 }
             }
 
-            if is_match {
-                if on_match(MatchedPattern {
-                    pattern_id: PatternId(pat.id),
-                    start: start,
-                    end: end,
-                }) == Scan::Stop
-                {
-                    return false;
+            hits.positions = 0;
+            if arm32_any(vorrq_u8(low_buckets, high_buckets)) {
+                vst1q_u8(hits.low.as_mut_ptr(), low_buckets);
+                vst1q_u8(hits.high.as_mut_ptr(), high_buckets);
+                hits.mark_positions();
+            }
+        }
+    }
+}
+
+/// The equivalent of `vqtbl1q_u8` built from two `vtbl2_u8` lookups.
+#[cfg(target_arch = "arm")]
+#[target_feature(enable = "neon")]
+#[inline]
+unsafe fn arm32_lookup(table: uint8x8x2_t, indices: uint8x16_t) -> uint8x16_t {
+    unsafe {
+        vcombine_u8(
+            vtbl2_u8(table, vget_low_u8(indices)),
+            vtbl2_u8(table, vget_high_u8(indices)),
+        )
+    }
+}
+
+#[cfg(target_arch = "arm")]
+#[target_feature(enable = "neon")]
+#[inline]
+unsafe fn arm32_any(v: uint8x16_t) -> bool {
+    unsafe {
+        let u32s: uint32x4_t = std::mem::transmute(v);
+        let t1 = vgetq_lane_u32(u32s, 0);
+        let t2 = vgetq_lane_u32(u32s, 1);
+        let t3 = vgetq_lane_u32(u32s, 2);
+        let t4 = vgetq_lane_u32(u32s, 3);
+        (t1 | t2 | t3 | t4) != 0
+    }
+}
+
+/// Up to [`MAX_BUCKETS`] buckets tested together in one pass.
+struct Group {
+    masks: NibbleMasks,
+    buckets: Vec<Bucket>,
+}
+
+impl Group {
+    /// Splits `keys`, in order, into one contiguous run per bucket.
+    fn new(keys: Vec<(Vec<u8>, Vec<PatternInfo>)>) -> Self {
+        let count = keys.len().min(MAX_BUCKETS);
+        let total = keys.len();
+        let mut masks = NibbleMasks::default();
+        let mut buckets: Vec<Bucket> = (0..count).map(|_| Bucket::default()).collect();
+
+        for (index, (key, patterns)) in keys.into_iter().enumerate() {
+            let bucket = index * count / total;
+            masks.insert(bucket, &key);
+            buckets[bucket].keys.push((key, patterns));
+        }
+        Group { masks, buckets }
+    }
+
+    /// Whether the group uses the second half of the masks.
+    fn is_wide(&self) -> bool {
+        self.buckets.len() > 8
+    }
+
+    /// Verifies the patterns of every key in the buckets set in `hits` that
+    /// starts at `anchor_pos`.
+    #[inline(always)]
+    fn verify(
+        &self,
+        table: &PatternTable,
+        data: &[u8],
+        anchor_pos: usize,
+        mut hits: u16,
+        out: &mut Ordered,
+    ) {
+        while hits != 0 {
+            let bucket = &self.buckets[hits.trailing_zeros() as usize];
+            hits &= hits - 1;
+
+            for (key, patterns) in &bucket.keys {
+                if data[anchor_pos..].starts_with(key) {
+                    table.find_all(data, anchor_pos, patterns, out);
                 }
             }
         }
-        true
     }
 }
 
+#[derive(Default)]
 struct Bucket {
-    #[cfg(target_arch = "x86_64")]
-    fingerprint_avx: [Option<__m256i>; 3],
-    #[cfg(any(target_arch = "aarch64", target_arch = "arm"))]
-    fingerprint_neon: [Option<uint8x16_t>; 3],
-    fingerprint_bytes: Vec<u8>,
+    keys: Vec<(Vec<u8>, Vec<PatternInfo>)>,
+}
 
-    patterns: Vec<common::PatternInfo>,
+/// For each key position and nibble value, the buckets holding a key whose
+/// byte at that position has that nibble. Buckets 0-7 are the bits of the
+/// first half, 8-15 those of the second, so each half is a vector shuffle
+/// table.
+#[derive(Default)]
+struct NibbleMasks {
+    lo: [[[u8; 16]; PREFILTER_LEN]; 2],
+    hi: [[[u8; 16]; PREFILTER_LEN]; 2],
+}
+
+impl NibbleMasks {
+    fn insert(&mut self, bucket: usize, key: &[u8]) {
+        let (half, bit) = (bucket / 8, 1 << (bucket % 8));
+
+        for k in 0..PREFILTER_LEN {
+            match key.get(k) {
+                Some(&byte) => {
+                    self.lo[half][k][(byte & 0x0F) as usize] |= bit;
+                    self.hi[half][k][(byte >> 4) as usize] |= bit;
+                }
+                // a short key accepts any byte past its end
+                None => {
+                    self.lo[half][k].iter_mut().for_each(|m| *m |= bit);
+                    self.hi[half][k].iter_mut().for_each(|m| *m |= bit);
+                }
+            }
+        }
+    }
+
+    /// The buckets that accept `byte` at key position `k`.
+    #[inline(always)]
+    fn buckets(&self, k: usize, byte: u8) -> u16 {
+        let (lo, hi) = ((byte & 0x0F) as usize, (byte >> 4) as usize);
+        let half = |h: usize| self.lo[h][k][lo] & self.hi[h][k][hi];
+        half(0) as u16 | (half(1) as u16) << 8
+    }
 }
@@ -0,0 +1,356 @@
+//! Importer for IDA FLIRT `.pat` files.
+//!
+//! Each line describes one function:
+//!
+//! ```text
+//! 558BEC83EC..53565733DB 0B 8A4E 0041 :0000 _memcpy ^0010 _helper ....8B45
+//! ```
+//!
+//! The leading bytes (`..` is a wildcard) and the optional tail bytes become
+//! the pattern. The bytes between them are covered by a CRC16 that
+//! [`FlirtSignatures::verify`] checks after a match, since it cannot be
+//! expressed as a pattern.
+
+use std::{fmt::Display, path::Path};
+
+use crate::{
+    Hexpotter, MatchedPattern, Pattern, PatternError, PatternId,
+    source::{self, LoadError, SourceError},
+};
+
+/// Number of leading bytes stored before the CRC region.
+const LEADING_LEN: usize = 32;
+
+/// A name defined by a FLIRT signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlirtName {
+    offset: usize,
+    name: String,
+    local: bool,
+}
+
+impl FlirtName {
+    /// Offset of the name from the start of the function.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the name is local (`:XXXX@`) rather than public.
+    pub fn is_local(&self) -> bool {
+        self.local
+    }
+}
+
+/// A function signature loaded from a `.pat` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlirtSignature {
+    pattern: Pattern,
+    crc_len: usize,
+    crc: u16,
+    function_len: usize,
+    names: Vec<FlirtName>,
+    references: Vec<FlirtName>,
+    line: usize,
+}
+
+impl FlirtSignature {
+    pub fn pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+
+    /// Length of the whole function in bytes.
+    pub fn function_len(&self) -> usize {
+        self.function_len
+    }
+
+    /// Public names defined by the function, such as its own symbol.
+    pub fn public_names(&self) -> impl Iterator<Item = &FlirtName> {
+        self.names.iter().filter(|n| !n.local)
+    }
+
+    /// Every name defined by the function, local ones included.
+    pub fn names(&self) -> &[FlirtName] {
+        &self.names
+    }
+
+    /// Names referenced from within the function (`^XXXX name`).
+    pub fn references(&self) -> &[FlirtName] {
+        &self.references
+    }
+
+    /// 1-based line of the function in the `.pat` file.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Checks the CRC16 over the bytes following the leading bytes of a
+    /// function starting at `start`.
+    pub fn check_crc(&self, data: &[u8], start: usize) -> bool {
+        if self.crc_len == 0 {
+            return true;
+        }
+        let Some(region) = start
+            .checked_add(LEADING_LEN)
+            .and_then(|from| data.get(from..from.checked_add(self.crc_len)?))
+        else {
+            return false;
+        };
+        crc16(region) == self.crc
+    }
+}
+
+/// The signatures of a FLIRT `.pat` file, compiled to patterns.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FlirtSignatures {
+    signatures: Vec<FlirtSignature>,
+}
+
+impl FlirtSignatures {
+    /// Parses the contents of a `.pat` file, returning every error found.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexpotter::{FlirtSignatures, Scan};
+    /// let pat = "\
+    ///     558BEC8B4508..C3 00 0000 0008 :0000 _get_arg\n\
+    ///     ---\n";
+    /// let signatures = FlirtSignatures::parse(pat).unwrap();
+    /// let scanner = signatures.scanner();
+    ///
+    /// let data = [0xCC, 0x55, 0x8B, 0xEC, 0x8B, 0x45, 0x08, 0x5D, 0xC3];
+    /// scanner.scan(&data, |m| {
+    ///     assert!(signatures.verify(&m, &data));
+    ///     let signature = signatures.get(m.id()).unwrap();
+    ///     assert_eq!(signature.public_names().next().unwrap().name(), "_get_arg");
+    ///     Scan::Continue
+    /// });
+    /// ```
+    pub fn parse(source: &str) -> Result<Self, Vec<FlirtError>> {
+        let mut signatures = Vec::new();
+        let mut errors = Vec::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let indent = line.len() - line.trim_start().len();
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line == "---" {
+                break;
+            }
+
+            match parse_line(line, index + 1, indent) {
+                Ok(signature) => signatures.push(signature),
+                Err(kind) => errors.push(FlirtError {
+                    line: index + 1,
+                    kind,
+                }),
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(FlirtSignatures { signatures })
+    }
+
+    /// Reads and parses the `.pat` file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LoadError<FlirtError>> {
+        source::load(path, Self::parse)
+    }
+
+    /// A scanner over the leading and tail bytes of every function, reporting
+    /// the function's index for [`FlirtSignatures::get`] as its
+    /// [`PatternId`]. The bytes under the CRC are not checked until
+    /// [`FlirtSignatures::verify`].
+    pub fn scanner(&self) -> Hexpotter {
+        Hexpotter::from_patterns(self.signatures.iter().map(|s| s.pattern.clone()))
+    }
+
+    /// Checks the CRC of a match reported by [`FlirtSignatures::scanner`].
+    pub fn verify(&self, matched: &MatchedPattern, data: &[u8]) -> bool {
+        self.get(matched.id())
+            .is_some_and(|s| s.check_crc(data, matched.start()))
+    }
+
+    pub fn get(&self, id: PatternId) -> Option<&FlirtSignature> {
+        self.signatures.get(id.usize())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (PatternId, &FlirtSignature)> {
+        self.signatures
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (PatternId(i), s))
+    }
+
+    pub fn len(&self) -> usize {
+        self.signatures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.signatures.is_empty()
+    }
+}
+
+/// Parses the function on line `number`, whose text starts `column` bytes
+/// into the line.
+fn parse_line(line: &str, number: usize, column: usize) -> Result<FlirtSignature, FlirtErrorKind> {
+    let mut fields = line.split_ascii_whitespace();
+    let mut field = || fields.next().ok_or(FlirtErrorKind::MissingField);
+
+    let leading = field()?;
+    let crc_len = hex_number(field()?)?;
+    let crc = u16::try_from(hex_number(field()?)?).map_err(|_| FlirtErrorKind::InvalidNumber)?;
+    let function_len = hex_number(field()?)?;
+    // the CRC covers at most 255 bytes, all of them inside the function
+    let crc_end = LEADING_LEN
+        .checked_add(crc_len)
+        .filter(|&end| crc_len <= 0xFF && (crc_len == 0 || end <= function_len))
+        .ok_or(FlirtErrorKind::InvalidNumber)?;
+
+    let mut names = Vec::new();
+    let mut references = Vec::new();
+    let mut tail = None;
+
+    while let Some(field) = fields.next() {
+        let (list, offset) = if let Some(offset) = field.strip_prefix(':') {
+            (&mut names, offset)
+        } else if let Some(offset) = field.strip_prefix('^') {
+            (&mut references, offset)
+        } else {
+            if fields.next().is_some() {
+                return Err(FlirtErrorKind::UnexpectedField);
+            }
+            tail = Some(field);
+            break;
+        };
+
+        let (offset, local) = match offset.strip_suffix('@') {
+            Some(offset) => (offset, true),
+            None => (offset, false),
+        };
+        let name = fields.next().ok_or(FlirtErrorKind::MissingField)?;
+        list.push(FlirtName {
+            offset: hex_number(offset)?,
+            name: name.to_owned(),
+            local,
+        });
+    }
+
+    let mut bytes = hex_bytes(leading)?;
+    if bytes.len() > LEADING_LEN {
+        return Err(FlirtErrorKind::InvalidBytes);
+    }
+    if let Some(tail) = tail {
+        bytes.resize(crc_end, None);
+        bytes.extend(hex_bytes(tail)?);
+    }
+    while bytes.last() == Some(&None) {
+        bytes.pop();
+    }
+
+    let pattern = bytes
+        .iter()
+        .fold(Pattern::builder(), |builder, byte| match byte {
+            Some(value) => builder.byte(*value),
+            None => builder.wildcard(),
+        })
+        .build()
+        .map_err(|e| FlirtErrorKind::Pattern(e.with_column(column)))?;
+
+    Ok(FlirtSignature {
+        pattern,
+        crc_len,
+        crc,
+        function_len,
+        names,
+        references,
+        line: number,
+    })
+}
+
+fn hex_number(field: &str) -> Result<usize, FlirtErrorKind> {
+    usize::from_str_radix(field, 16).map_err(|_| FlirtErrorKind::InvalidNumber)
+}
+
+/// Decodes pairs of hex digits, with `..` as a wildcard.
+fn hex_bytes(field: &str) -> Result<Vec<Option<u8>>, FlirtErrorKind> {
+    if !field.len().is_multiple_of(2) {
+        return Err(FlirtErrorKind::InvalidBytes);
+    }
+    field
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            b".." => Ok(None),
+            _ => std::str::from_utf8(pair)
+                .ok()
+                .and_then(|s| u8::from_str_radix(s, 16).ok())
+                .map(Some)
+                .ok_or(FlirtErrorKind::InvalidBytes),
+        })
+        .collect()
+}
+
+/// The CRC16 used by FLIRT: reflected CCITT, inverted and byte-swapped.
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &byte in data {
+        let mut byte = byte;
+        for _ in 0..8 {
+            crc = if (crc ^ byte as u16) & 1 != 0 {
+                (crc >> 1) ^ 0x8408
+            } else {
+                crc >> 1
+            };
+            byte >>= 1;
+        }
+    }
+    (!crc).swap_bytes()
+}
+
+#[derive(Debug)]
+pub enum FlirtErrorKind {
+    /// The line ends before all required fields.
+    MissingField,
+    /// A field follows the tail bytes.
+    UnexpectedField,
+    /// A field is not a hex number, or the CRC length is over `FF` or runs
+    /// past the end of the function.
+    InvalidNumber,
+    /// Leading or tail bytes are not pairs of hex digits or `..`.
+    InvalidBytes,
+    /// The bytes do not form a pattern, e.g. when all of them are `..`. The
+    /// column is that of the leading bytes within the line.
+    Pattern(PatternError),
+}
+
+/// An error in a `.pat` file, on the line of the offending function.
+pub type FlirtError = SourceError<FlirtErrorKind>;
+
+impl Display for FlirtErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FlirtErrorKind::MissingField => write!(f, "missing field"),
+            FlirtErrorKind::UnexpectedField => write!(f, "unexpected field after tail bytes"),
+            FlirtErrorKind::InvalidNumber => write!(f, "invalid hex number"),
+            FlirtErrorKind::InvalidBytes => write!(f, "invalid pattern bytes"),
+            FlirtErrorKind::Pattern(e) => write!(f, "{}", e.kind),
+        }
+    }
+}
+
+impl std::error::Error for FlirtErrorKind {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FlirtErrorKind::Pattern(e) => Some(e),
+            _ => None,
+        }
+    }
+}
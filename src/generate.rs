@@ -0,0 +1,683 @@
+//! Signature generation: the shortest pattern that uniquely identifies an
+//! offset in a buffer.
+//!
+//! Bytes that are likely to change between builds, such as x86 branch
+//! displacements, RIP-relative displacements and 32-bit immediates, are
+//! wildcarded before the pattern is grown. The heuristics work on raw bytes
+//! without disassembling, so `offset` should be the start of an instruction.
+
+use std::fmt::Display;
+
+use crate::{Hexpotter, Pattern, Scan};
+
+/// Default upper bound on the length of a generated pattern.
+const DEFAULT_MAX_LEN: usize = 64;
+
+/// Opcodes followed by a ModR/M byte whose `[rip+disp32]` form is wildcarded.
+const MODRM_OPCODES: &[u8] = &[
+    0x01, 0x03, 0x09, 0x0B, 0x21, 0x23, 0x29, 0x2B, 0x31, 0x33, 0x39, 0x3B, 0x63, 0x85, 0x87, 0x89,
+    0x8B, 0x8D,
+];
+
+/// Second bytes of `0F xx` opcodes handled like [`MODRM_OPCODES`].
+const MODRM_OPCODES_0F: &[u8] = &[
+    0x10, 0x11, 0x28, 0x29, 0x2E, 0x2F, 0x54, 0x57, 0x58, 0x59, 0x5C, 0x5E, 0xB6, 0xB7, 0xBE, 0xBF,
+];
+
+/// Grows patterns from an offset in `data` until they match exactly once.
+///
+/// # Example
+///
+/// ```rust
+/// # use hexpotter::Generator;
+/// let data = [
+///     0x48, 0x8B, 0x05, 0x10, 0x20, 0x30, 0x40, // mov rax, [rip+0x40302010]
+///     0xE8, 0x00, 0x01, 0x00, 0x00, // call +0x100
+///     0x48, 0x8B, 0x05, 0x11, 0x21, 0x31, 0x41, // mov rax, [rip+0x41312111]
+///     0xC3,
+/// ];
+///
+/// let signature = Generator::new(&data).generate(0).unwrap();
+/// assert_eq!(signature.pattern().to_string(), "48 8B 05 ?? ?? ?? ?? E8");
+/// assert_eq!(signature.matches(), [0]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Generator<'d> {
+    data: &'d [u8],
+    max_len: usize,
+    wildcard_operands: bool,
+}
+
+impl<'d> Generator<'d> {
+    pub fn new(data: &'d [u8]) -> Self {
+        Generator {
+            data,
+            max_len: DEFAULT_MAX_LEN,
+            wildcard_operands: true,
+        }
+    }
+
+    /// Sets the longest pattern to try, in bytes (64 by default).
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Whether displacements and immediates are wildcarded (on by default).
+    /// They are spotted by their opcode without disassembling, so a byte
+    /// inside another instruction can be taken for one.
+    pub fn wildcard_operands(mut self, enabled: bool) -> Self {
+        self.wildcard_operands = enabled;
+        self
+    }
+
+    /// Generates the shortest pattern starting at `offset` that matches only
+    /// there.
+    ///
+    /// When even a pattern of [`Generator::max_len`] bytes matches more than
+    /// once, the error carries that pattern and every offset it matched.
+    pub fn generate(&self, offset: usize) -> Result<GeneratedSignature, GenerateError> {
+        if offset >= self.data.len() {
+            return Err(GenerateError::OutOfBounds);
+        }
+
+        let window = &self.data[offset..self.data.len().min(offset + self.max_len)];
+        let mut bytes: Vec<Option<u8>> = window.iter().copied().map(Some).collect();
+        if self.wildcard_operands {
+            wildcard_operands(&mut bytes);
+        }
+
+        let longest = prefix(&bytes, bytes.len());
+        if longest.is_none() || !self.is_unique(longest.as_ref()) {
+            let pattern = longest.unwrap_or_else(Pattern::new);
+            let matches = self.matches(&pattern);
+            return Err(GenerateError::NotUnique(Box::new(GeneratedSignature {
+                pattern,
+                offset,
+                matches,
+            })));
+        }
+
+        // uniqueness only grows with the length, so search for the shortest
+        let (mut lo, mut hi) = (1, bytes.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.is_unique(prefix(&bytes, mid).as_ref()) {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+
+        let pattern = prefix(&bytes, lo).expect("unique prefix has a fixed byte");
+        let matches = self.matches(&pattern);
+        Ok(GeneratedSignature {
+            pattern,
+            offset,
+            matches,
+        })
+    }
+
+    fn is_unique(&self, pattern: Option<&Pattern>) -> bool {
+        let Some(pattern) = pattern else {
+            return false;
+        };
+
+        let mut count = 0;
+        Hexpotter::from_patterns([pattern.clone()]).scan(self.data, |_| {
+            count += 1;
+            if count > 1 {
+                Scan::Stop
+            } else {
+                Scan::Continue
+            }
+        });
+        count == 1
+    }
+
+    fn matches(&self, pattern: &Pattern) -> Vec<usize> {
+        if pattern.is_empty() {
+            return Vec::new();
+        }
+        starts(&Hexpotter::from_patterns([pattern.clone()]), self.data)
+    }
+}
+
+/// Start offsets of every match of `scanner` in `data`, in ascending order.
+fn starts(scanner: &Hexpotter, data: &[u8]) -> Vec<usize> {
+    let mut starts = Vec::new();
+    scanner.scan(data, |m| {
+        starts.push(m.start());
+        Scan::Continue
+    });
+    starts
+}
+
+/// Builds a pattern from the first `len` bytes, without trailing wildcards.
+/// Returns `None` when none of them is fixed.
+fn prefix(bytes: &[Option<u8>], len: usize) -> Option<Pattern> {
+    let end = bytes[..len].iter().rposition(Option::is_some)? + 1;
+    let builder = bytes[..end]
+        .iter()
+        .fold(Pattern::builder(), |builder, byte| match byte {
+            Some(value) => builder.byte(*value),
+            None => builder.wildcard(),
+        });
+    builder.build().ok()
+}
+
+/// Wildcards x86 operands that usually differ between builds: branch and
+/// RIP-relative displacements, and 32-bit immediates that may be addresses.
+///
+/// The bytes are not disassembled, so instruction boundaries are unknown.
+/// Every byte not already wildcarded is taken as a possible opcode, which
+/// can also wildcard four bytes after an operand byte that merely looks like
+/// one of these opcodes. Wildcarding too much only makes the pattern longer,
+/// while a missed displacement makes it break on the next build.
+fn wildcard_operands(bytes: &mut [Option<u8>]) {
+    let byte = |bytes: &[Option<u8>], i: usize| bytes.get(i).copied().flatten();
+    let is_rip = |modrm: Option<u8>| modrm.is_some_and(|m| m & 0xC7 == 0x05);
+
+    let mut i = 0;
+    while i < bytes.len() {
+        // skip operand-size and REX prefixes
+        let mut op = i;
+        while matches!(byte(bytes, op), Some(0x66 | 0x40..=0x4F)) {
+            op += 1;
+        }
+        let Some(opcode) = byte(bytes, op) else {
+            break;
+        };
+
+        let operand = match (opcode, byte(bytes, op + 1)) {
+            // call/jmp rel32, push imm32
+            (0xE8 | 0xE9 | 0x68, _) => Some(op + 1),
+            // mov r32, imm32
+            (0xB8..=0xBF, _) => Some(op + 1),
+            // jcc rel32
+            (0x0F, Some(0x80..=0x8F)) => Some(op + 2),
+            (0x0F, Some(second)) if MODRM_OPCODES_0F.contains(&second) => {
+                is_rip(byte(bytes, op + 2)).then_some(op + 3)
+            }
+            // call/jmp [rip+disp32], mov dword [rip+disp32], imm32
+            (0xFF | 0xC7, modrm) if is_rip(modrm) => Some(op + 2),
+            (_, modrm) if MODRM_OPCODES.contains(&opcode) && is_rip(modrm) => Some(op + 2),
+            _ => None,
+        };
+
+        match operand {
+            Some(start) => {
+                let end = bytes.len().min(start + 4);
+                bytes[start..end].fill(None);
+                i = end;
+            }
+            None => i = op + 1,
+        }
+    }
+}
+
+/// A pattern produced by [`Generator::generate`] and the offsets it matched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratedSignature {
+    pattern: Pattern,
+    offset: usize,
+    matches: Vec<usize>,
+}
+
+impl GeneratedSignature {
+    pub fn pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+
+    /// The offset the pattern was generated for.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Every offset the pattern matched, in ascending order.
+    pub fn matches(&self) -> &[usize] {
+        &self.matches
+    }
+
+    /// Whether the pattern matched only at [`GeneratedSignature::offset`].
+    pub fn is_unique(&self) -> bool {
+        self.matches == [self.offset]
+    }
+}
+
+/// Computes the most specific pattern shared by the same code in several
+/// builds, wildcarding the bytes and nibbles that differ.
+///
+/// # Example
+///
+/// ```rust
+/// # use hexpotter::Generalizer;
+/// let v1 = [0x90, 0x48, 0x8B, 0x05, 0x10, 0x20, 0x00, 0x00, 0xC3];
+/// let v2 = [0xCC, 0xCC, 0x48, 0x8B, 0x0D, 0x44, 0x21, 0x00, 0x00, 0xC3];
+///
+/// let signature = Generalizer::new()
+///     .sample(&v1, 1)
+///     .sample(&v2, 2)
+///     .generalize()
+///     .unwrap();
+/// assert_eq!(signature.pattern().to_string(), "48 8B 0? ?? 2? 00 00 C3");
+/// assert!(signature.is_unique());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Generalizer<'d> {
+    samples: Vec<(&'d [u8], usize)>,
+    max_len: usize,
+    gaps: bool,
+}
+
+impl Default for Generalizer<'_> {
+    fn default() -> Self {
+        Generalizer {
+            samples: Vec::new(),
+            max_len: DEFAULT_MAX_LEN,
+            gaps: false,
+        }
+    }
+}
+
+impl<'d> Generalizer<'d> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a build of the binary and the offset of the code in it.
+    pub fn sample(mut self, data: &'d [u8], offset: usize) -> Self {
+        self.samples.push((data, offset));
+        self
+    }
+
+    /// Sets the length of the compared region, in bytes of the first sample
+    /// (64 by default).
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Aligns the samples so that bytes inserted or removed between builds
+    /// become jumps instead of ending the pattern (off by default).
+    pub fn gaps(mut self, enabled: bool) -> Self {
+        self.gaps = enabled;
+        self
+    }
+
+    /// Builds the common pattern and scans every sample with it.
+    ///
+    /// The result is returned even when the pattern is not unique in every
+    /// sample; check [`GeneralizedSignature::is_unique`].
+    pub fn generalize(&self) -> Result<GeneralizedSignature, GenerateError> {
+        let Some(&(reference, offset)) = self.samples.first() else {
+            return Err(GenerateError::NoSamples);
+        };
+        if self
+            .samples
+            .iter()
+            .any(|&(data, offset)| offset >= data.len())
+        {
+            return Err(GenerateError::OutOfBounds);
+        }
+
+        let mut len = (reference.len() - offset).min(self.max_len);
+        if !self.gaps {
+            for &(data, offset) in &self.samples {
+                len = len.min(data.len() - offset);
+            }
+        }
+        let reference = &reference[offset..offset + len];
+
+        let alignments: Vec<Alignment> = self
+            .samples
+            .iter()
+            .map(|&(data, offset)| {
+                let sample = &data[offset..];
+                if self.gaps {
+                    Alignment::new(reference, &sample[..sample.len().min(2 * len)])
+                } else {
+                    Alignment::positional(&sample[..len])
+                }
+            })
+            .collect();
+
+        let pattern = common_pattern(len, &alignments)?;
+        let scanner = Hexpotter::from_patterns([pattern.clone()]);
+        let matches = self
+            .samples
+            .iter()
+            .map(|&(data, _)| starts(&scanner, data))
+            .collect();
+
+        Ok(GeneralizedSignature {
+            pattern,
+            offsets: self.samples.iter().map(|&(_, offset)| offset).collect(),
+            matches,
+        })
+    }
+}
+
+/// A sample aligned against the first one: the byte matched with every
+/// reference position, and the bytes inserted before each of them.
+struct Alignment {
+    aligned: Vec<Option<u8>>,
+    inserted: Vec<usize>,
+}
+
+impl Alignment {
+    fn positional(sample: &[u8]) -> Self {
+        Alignment {
+            aligned: sample.iter().copied().map(Some).collect(),
+            inserted: vec![0; sample.len()],
+        }
+    }
+
+    /// Global alignment of `reference` against a prefix of `sample`, with the
+    /// first bytes of both always paired.
+    fn new(reference: &[u8], sample: &[u8]) -> Self {
+        const MATCH: i32 = 2;
+        const MISMATCH: i32 = -1;
+        const GAP: i32 = -2;
+        // unreachable cells: nothing may precede the first pair
+        const NONE: i32 = i32::MIN / 2;
+
+        let (n, m) = (reference.len(), sample.len());
+        let width = m + 1;
+        let pair = |i: usize, j: usize| {
+            if reference[i - 1] == sample[j - 1] {
+                MATCH
+            } else {
+                MISMATCH
+            }
+        };
+
+        let mut score = vec![NONE; (n + 1) * width];
+        score[0] = 0;
+        for i in 1..=n {
+            for j in 1..=m {
+                score[i * width + j] = (score[(i - 1) * width + j - 1] + pair(i, j))
+                    .max(score[(i - 1) * width + j] + GAP)
+                    .max(score[i * width + j - 1] + GAP);
+            }
+        }
+
+        // the rest of the sample is free
+        let mut j = (1..=m).max_by_key(|&j| score[n * width + j]).unwrap_or(0);
+        let mut i = n;
+        let mut aligned = vec![None; n];
+        let mut inserted = vec![0; n];
+
+        while i > 0 && j > 0 {
+            let here = score[i * width + j];
+            if score[(i - 1) * width + j - 1] + pair(i, j) == here {
+                aligned[i - 1] = Some(sample[j - 1]);
+                i -= 1;
+                j -= 1;
+            } else if score[(i - 1) * width + j] + GAP == here {
+                i -= 1;
+            } else {
+                if i < n {
+                    inserted[i] += 1;
+                }
+                j -= 1;
+            }
+        }
+
+        Alignment { aligned, inserted }
+    }
+}
+
+enum Column {
+    Byte(u8, u8),
+    Jump(usize, usize),
+}
+
+/// Merges the aligned samples column by column: equal bytes stay, bytes
+/// sharing a nibble keep it, and runs whose length differs become jumps.
+fn common_pattern(len: usize, alignments: &[Alignment]) -> Result<Pattern, GenerateError> {
+    let mut columns = Vec::new();
+    let mut region: Option<Vec<usize>> = None;
+
+    for i in 0..len {
+        if alignments.iter().any(|a| a.inserted[i] > 0) {
+            let counts = region.get_or_insert_with(|| vec![0; alignments.len()]);
+            for (count, alignment) in counts.iter_mut().zip(alignments) {
+                *count += alignment.inserted[i];
+            }
+        }
+
+        let bytes: Option<Vec<u8>> = alignments.iter().map(|a| a.aligned[i]).collect();
+        let Some(bytes) = bytes else {
+            let counts = region.get_or_insert_with(|| vec![0; alignments.len()]);
+            for (count, alignment) in counts.iter_mut().zip(alignments) {
+                *count += alignment.aligned[i].is_some() as usize;
+            }
+            continue;
+        };
+
+        if let Some(counts) = region.take() {
+            let min = counts.iter().copied().min().unwrap_or(0);
+            let max = counts.iter().copied().max().unwrap_or(0);
+            columns.push(Column::Jump(min, max));
+        }
+
+        let (first, rest) = (bytes[0], &bytes[1..]);
+        let diff = rest.iter().fold(0, |diff, &b| diff | (b ^ first));
+        let mask = match diff {
+            0 => 0xFF,
+            0x01..=0x0F => 0xF0,
+            _ if diff & 0x0F == 0 => 0x0F,
+            _ => 0x00,
+        };
+        columns.push(Column::Byte(first & mask, mask));
+    }
+
+    while matches!(
+        columns.last(),
+        Some(Column::Jump(..) | Column::Byte(_, 0x00))
+    ) {
+        columns.pop();
+    }
+
+    columns
+        .iter()
+        .fold(Pattern::builder(), |builder, column| match *column {
+            Column::Byte(value, mask) => builder.masked(value, mask),
+            Column::Jump(min, max) => builder.jump(min, max),
+        })
+        .build()
+        .map_err(|_| GenerateError::NoCommonBytes)
+}
+
+/// A pattern produced by [`Generalizer::generalize`] and the offsets it
+/// matched in each sample.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneralizedSignature {
+    pattern: Pattern,
+    offsets: Vec<usize>,
+    matches: Vec<Vec<usize>>,
+}
+
+impl GeneralizedSignature {
+    pub fn pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+
+    /// Every offset the pattern matched in the `sample`-th sample, in
+    /// ascending order.
+    pub fn matches(&self, sample: usize) -> &[usize] {
+        &self.matches[sample]
+    }
+
+    /// Whether the pattern matched exactly once in every sample, at the
+    /// offset it was given for.
+    pub fn is_unique(&self) -> bool {
+        self.matches
+            .iter()
+            .zip(&self.offsets)
+            .all(|(matches, &offset)| matches == &[offset])
+    }
+}
+
+/// Shrinks an existing pattern against a corpus: trailing positions are
+/// dropped and bytes wildcarded for as long as the pattern still matches at
+/// exactly the same offsets.
+///
+/// Positions used by the target marker or captures are kept.
+///
+/// # Example
+///
+/// ```rust
+/// # use hexpotter::{Minimizer, Pattern};
+/// let pattern: Pattern = "48 8B 05 10 20 30 40 C3".parse().unwrap();
+/// let corpus = [0x90, 0x48, 0x8B, 0x05, 0x10, 0x20, 0x30, 0x40, 0xC3, 0x48, 0x8B, 0x0D];
+///
+/// let minimized = Minimizer::new(&pattern).sample(&corpus).minimize().unwrap();
+/// assert_eq!(minimized.pattern().to_string(), "?? ?? 05");
+/// assert_eq!(minimized.removed(), 5);
+/// assert_eq!(minimized.wildcarded(), [0, 1]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Minimizer<'p, 'd> {
+    pattern: &'p Pattern,
+    corpus: Vec<&'d [u8]>,
+}
+
+impl<'p, 'd> Minimizer<'p, 'd> {
+    pub fn new(pattern: &'p Pattern) -> Self {
+        Minimizer {
+            pattern,
+            corpus: Vec::new(),
+        }
+    }
+
+    /// Adds a buffer whose matches must be preserved.
+    pub fn sample(mut self, data: &'d [u8]) -> Self {
+        self.corpus.push(data);
+        self
+    }
+
+    pub fn minimize(&self) -> Result<MinimizedSignature, GenerateError> {
+        if self.corpus.is_empty() {
+            return Err(GenerateError::NoSamples);
+        }
+
+        let expected = self.matches(self.pattern);
+        let same = |pattern: &Pattern| self.matches(pattern) == expected;
+
+        // shorter prefixes only add matches, so search for the shortest
+        let mut pattern = self.pattern.clone();
+        let (mut lo, mut hi) = (self.pattern.required_len().max(1), self.pattern.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let mut shorter = self.pattern.clone();
+            shorter.truncate(mid);
+            if same(&shorter) {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        pattern.truncate(lo);
+
+        // negated positions have no mask bits but still restrict the byte
+        let wild = |pattern: &Pattern, p: usize| pattern.position_class(p).len() == 256;
+
+        let mut wildcarded = Vec::new();
+        for position in 0..pattern.len() {
+            let fixed = (0..pattern.len()).filter(|&p| !wild(&pattern, p)).count();
+            if wild(&pattern, position) || fixed == 1 {
+                continue;
+            }
+
+            let mut candidate = pattern.clone();
+            candidate.clear(position);
+            if same(&candidate) {
+                pattern = candidate;
+                wildcarded.push(position);
+            }
+        }
+
+        // wildcarding may have left wildcards at the end
+        let mut len = pattern.len();
+        while len > pattern.required_len().max(1) && wild(&pattern, len - 1) {
+            len -= 1;
+        }
+        let mut trimmed = pattern.clone();
+        trimmed.truncate(len);
+        if same(&trimmed) {
+            pattern = trimmed;
+            wildcarded.retain(|&p| p < len);
+        }
+
+        Ok(MinimizedSignature {
+            removed: self.pattern.len() - pattern.len(),
+            pattern,
+            wildcarded,
+        })
+    }
+
+    /// Start offsets of every match in each corpus buffer, in ascending order.
+    fn matches(&self, pattern: &Pattern) -> Vec<Vec<usize>> {
+        let scanner = Hexpotter::from_patterns([pattern.clone()]);
+        self.corpus
+            .iter()
+            .map(|data| starts(&scanner, data))
+            .collect()
+    }
+}
+
+/// A pattern shrunk by [`Minimizer::minimize`] and what was taken out of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinimizedSignature {
+    pattern: Pattern,
+    removed: usize,
+    wildcarded: Vec<usize>,
+}
+
+impl MinimizedSignature {
+    pub fn pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+
+    /// Number of positions dropped from the end of the pattern.
+    pub fn removed(&self) -> usize {
+        self.removed
+    }
+
+    /// Positions of the remaining pattern that were turned into wildcards.
+    pub fn wildcarded(&self) -> &[usize] {
+        &self.wildcarded
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GenerateError {
+    /// An offset lies outside its buffer.
+    OutOfBounds,
+    /// [`Generalizer`] or [`Minimizer`] was given no samples.
+    NoSamples,
+    /// The samples share no fixed byte or nibble.
+    NoCommonBytes,
+    /// The longest allowed pattern still matches more than once.
+    NotUnique(Box<GeneratedSignature>),
+}
+
+impl Display for GenerateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GenerateError::OutOfBounds => write!(f, "offset is outside the buffer"),
+            GenerateError::NoSamples => write!(f, "no samples given"),
+            GenerateError::NoCommonBytes => write!(f, "samples have no byte in common"),
+            GenerateError::NotUnique(signature) => write!(
+                f,
+                "`{}` still matches {} times",
+                signature.pattern,
+                signature.matches.len()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GenerateError {}
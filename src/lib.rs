@@ -1,33 +1,89 @@
-use crate::engine::{LookupEngine, MatchedPattern, Scan, anchor::Anchor};
+use crate::engine::{LookupEngine, anchor::Anchor, common, scalar::Scalar, teddy::Teddy};
 
+pub mod capture;
 pub mod engine;
+pub mod flirt;
+pub mod generate;
+pub mod lint;
 pub mod pattern;
+pub mod resolve;
+pub mod signature;
+pub mod source;
+pub mod yara;
+
+pub use crate::capture::{Capture, CaptureType, CaptureValue, Captures, Endian};
+pub use crate::engine::{AnchorSet, ByteFrequencies, EngineKind, MatchedPattern, Scan, SimdPath};
+pub use crate::flirt::{FlirtError, FlirtErrorKind, FlirtName, FlirtSignature, FlirtSignatures};
+pub use crate::generate::{
+    GeneralizedSignature, Generalizer, GenerateError, GeneratedSignature, Generator,
+    MinimizedSignature, Minimizer,
+};
+pub use crate::lint::{Lint, LintKind, Severity, lint};
+pub use crate::pattern::constant::StaticPattern;
+pub use crate::pattern::{
+    Dialect, Pattern, PatternBuilder, PatternError, PatternErrorKind, PatternId,
+};
+pub use crate::resolve::Relative;
+pub use crate::signature::{Signature, SignatureError, SignatureErrorKind, Signatures};
+pub use crate::source::{LoadError, SourceError};
+pub use crate::yara::{YaraError, YaraErrorKind, YaraRules, YaraString};
 
 /// A high-performance, multi-pattern binary scanner that automatically selects
 /// the optimal search algorithm based on available CPU features.
 pub struct Hexpotter {
     engine: Box<dyn engine::LookupEngine>,
+    patterns: Vec<Pattern>,
+    anchors: Vec<AnchorSet>,
 }
 
 impl Hexpotter {
+    /// Starts configuring a scanner, e.g. to choose anchors from the byte
+    /// frequencies of a sample of the data to be scanned.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexpotter::Hexpotter;
+    /// let sample = [0x00, 0x00, 0x00, 0x00, 0xE8, 0x4C, 0x00, 0x00];
+    /// let scanner = Hexpotter::builder()
+    ///     .sample(&sample)
+    ///     .build(["00 00 00 E8 4C"])
+    ///     .unwrap();
+    /// ```
+    pub fn builder() -> HexpotterBuilder {
+        HexpotterBuilder::new()
+    }
+
     /// Creates a new `Hexpotter` instance optimized for the current CPU architecture.
     ///
     /// This constructor performs runtime feature detection to choose the fastest
     /// available engine:
     ///
     /// * **x86_64**: Uses **AVX2** SIMD engine if available.
-    /// * **AArch64 / ARM**: Uses **NEON** SIMD engine if available.
+    /// * **AArch64**: Uses **NEON** SIMD engine if available.
+    /// * **32-bit ARM**: Uses **NEON** SIMD engine when the build targets it
+    ///   (`target_feature = "neon"`), since it cannot be detected at runtime.
     /// * **Fallback**: Defaults to an Aho-Corasick + Anchors based engine if no SIMD
-    ///  features are detected.
+    ///   features are detected, or when the patterns have too many distinct
+    ///   anchor prefixes for the SIMD engine to stay faster, as with
+    ///   thousands of case-insensitive strings.
+    ///
+    /// Use [`HexpotterBuilder::engine`] to choose the engine yourself.
     ///
     /// # Arguments
     ///
     /// * `patterns` - An iterator of string slices representing the hex patterns
     ///   to compile (e.g., `vec!["FF ?? AA", "E8 ?? ?? ?? ??"]`).
     ///
+    /// # Panics
+    ///
+    /// Panics if any pattern fails to compile. Use [`Hexpotter::try_new`] to
+    /// handle malformed patterns gracefully.
+    ///
     /// # Example
     ///
     /// ```rust
+    /// # use hexpotter::Hexpotter;
     /// let scanner = Hexpotter::new([
     ///     "48 89 5C 24 08",
     ///     "E8 ?? ?? ?? ?? 48 89 44 24",
@@ -37,38 +93,67 @@ impl Hexpotter {
     where
         I: IntoIterator<Item = &'s str>,
     {
-        // choose the best engine based on the current architecture and features.
-        #[cfg(target_arch = "x86_64")]
-        if is_x86_feature_detected!("avx2") {
-            use crate::engine::teddy::Teddy;
-
-            return Self {
-                engine: Box::new(Teddy::new(patterns)),
-            };
-        }
-
-        #[cfg(target_arch = "aarch64")]
-        {
-            use crate::engine::teddy::Teddy;
-
-            return Self {
-                engine: Box::new(Teddy::new(patterns)),
-            };
-        }
+        Self::try_new(patterns).unwrap_or_else(|errors| panic!("{}", errors[0]))
+    }
 
-        #[cfg(target_arch = "arm")]
-        {
-            use crate::engine::teddy::Teddy;
+    /// Creates a new `Hexpotter` instance from already compiled patterns, such
+    /// as those made with [`Pattern::builder`] or [`pattern!`]. Pattern IDs
+    /// follow the iteration order.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexpotter::{Hexpotter, Pattern};
+    /// let call = Pattern::builder().byte(0xE8).wildcards(4).build().unwrap();
+    /// let mov = "48 8B 05".parse::<Pattern>().unwrap();
+    ///
+    /// let scanner = Hexpotter::from_patterns([call, mov]);
+    /// ```
+    pub fn from_patterns<I>(patterns: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Pattern>,
+    {
+        Self::builder().build_patterns(patterns)
+    }
 
-            return Self {
-                engine: Box::new(Teddy::new(patterns)),
-            };
-        }
+    /// Creates a new `Hexpotter` instance, returning every pattern that failed
+    /// to compile instead of panicking.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexpotter::{Hexpotter, PatternErrorKind};
+    /// let errors = Hexpotter::try_new(["48 8B", "4G ??", "48 ???"]).err().unwrap();
+    ///
+    /// assert_eq!(errors.len(), 2);
+    /// assert_eq!(errors[0].pattern_index(), 1);
+    /// assert_eq!(errors[0].column(), 1);
+    /// assert_eq!(errors[0].kind(), PatternErrorKind::InvalidHexDigit('G'));
+    /// assert_eq!(errors[1].kind(), PatternErrorKind::InvalidWildcard);
+    /// ```
+    pub fn try_new<'s, I>(patterns: I) -> Result<Self, Vec<PatternError>>
+    where
+        I: IntoIterator<Item = &'s str>,
+    {
+        Self::try_with_dialect(patterns, Dialect::Auto)
+    }
 
-        #[allow(unreachable_code)]
-        Self {
-            engine: Box::new(Anchor::new(patterns)),
-        }
+    /// Like [`Hexpotter::try_new`], but parses every pattern in the given
+    /// [`Dialect`] instead of detecting it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexpotter::{Dialect, Hexpotter};
+    /// let scanner = Hexpotter::try_with_dialect(["48 8B ? ? 05", "E8 ? ? ? ?"], Dialect::Ida);
+    /// assert!(scanner.is_ok());
+    /// ```
+    pub fn try_with_dialect<'s, I>(patterns: I, dialect: Dialect) -> Result<Self, Vec<PatternError>>
+    where
+        I: IntoIterator<Item = &'s str>,
+    {
+        Self::builder().dialect(dialect).build(patterns)
     }
 
     /// Scans the provided byte slice for occurrences of the compiled patterns.
@@ -77,6 +162,10 @@ impl Hexpotter {
     /// about the match (Pattern ID and offset). The closure must return a `Scan` enum
     /// to control the scanning process (e.g., continue searching or stop).
     ///
+    /// Matches are delivered in ascending order of [`MatchedPattern::start`],
+    /// ties broken by [`PatternId`], whichever engine is used, so stopping at
+    /// the first match reports the first occurrence.
+    ///
     /// # Arguments
     ///
     /// * `data` - The binary data to scan.
@@ -86,15 +175,241 @@ impl Hexpotter {
     /// # Example
     ///
     /// ```rust
+    /// # use hexpotter::{Hexpotter, Scan};
+    /// # let scanner = Hexpotter::new(["48 89 5C 24 08"]);
+    /// # let binary_data = [0x90, 0x48, 0x89, 0x5C, 0x24, 0x08];
     /// scanner.scan(&binary_data, |match_ctx| {
-    ///     println!("Found pattern {} at offset 0x{:X}", match_ctx.id, match_ctx.offset);
+    ///     println!("Found pattern {} at offset 0x{:X}", match_ctx.id(), match_ctx.start());
     ///     Scan::Continue
     /// });
     /// ```
+    ///
+    /// Finding the first occurrence of any pattern:
+    ///
+    /// ```rust
+    /// # use hexpotter::{Hexpotter, PatternId, Scan};
+    /// let scanner = Hexpotter::new(["5C 24", "48 89 5C"]);
+    /// let data = [0x90, 0x48, 0x89, 0x5C, 0x24, 0x08];
+    ///
+    /// let mut first = None;
+    /// scanner.scan(&data, |m| {
+    ///     first = Some((m.start(), m.id()));
+    ///     Scan::Stop
+    /// });
+    /// assert_eq!(first, Some((1, PatternId(1))));
+    /// ```
     pub fn scan<F>(&self, data: &[u8], mut on_match: F)
     where
         F: FnMut(MatchedPattern) -> Scan,
     {
         self.engine.scan(data, &mut on_match);
     }
+
+    /// Decodes the `<name:type>` captures of a match reported by [`Hexpotter::scan`].
+    ///
+    /// `data` must be the buffer that produced `matched`. Captures are empty
+    /// when it is too short to hold the match.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexpotter::{CaptureValue, Hexpotter, Scan};
+    /// let scanner = Hexpotter::new(["48 8B 05 <disp:i32le>"]);
+    /// let data = [0x48, 0x8B, 0x05, 0xF0, 0xFF, 0xFF, 0xFF];
+    ///
+    /// scanner.scan(&data, |m| {
+    ///     let captures = scanner.captures(&m, &data);
+    ///     assert_eq!(captures.get("disp"), Some(CaptureValue::I32(-16)));
+    ///     Scan::Continue
+    /// });
+    /// ```
+    pub fn captures(&self, matched: &MatchedPattern, data: &[u8]) -> Captures<'_> {
+        let pattern = &self.patterns[matched.id().usize()];
+        if pattern.captures.is_empty() {
+            return Captures::default();
+        }
+        let Some(offsets) = pattern.locate(data, matched.start(), matched.end()) else {
+            return Captures::default();
+        };
+
+        let captures = pattern
+            .captures
+            .iter()
+            .filter_map(|spec| {
+                let offset = offsets[spec.at];
+                Some(Capture {
+                    name: &spec.name,
+                    offset,
+                    value: spec.ty.decode(data.get(offset..)?)?,
+                })
+            })
+            .collect();
+        Captures { captures }
+    }
+
+    /// Follows the `=>` resolution chain declared by the matched pattern,
+    /// starting from [`MatchedPattern::target`], and returns the final offset
+    /// in `data`. A pattern without a chain resolves to its target.
+    ///
+    /// Returns `None` when a displacement lies outside `data` or a target
+    /// would be negative.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexpotter::{Hexpotter, Scan};
+    /// // 0x00: call 0x08; 0x08: jmp 0x20
+    /// let data = [0xE8, 0x03, 0x00, 0x00, 0x00, 0x90, 0x90, 0x90, 0xE9, 0x13, 0x00, 0x00, 0x00];
+    /// let scanner = Hexpotter::new(["E8 ?? ?? ?? ?? 90 => call => jmp"]);
+    ///
+    /// scanner.scan(&data, |m| {
+    ///     assert_eq!(scanner.resolve(&m, &data), Some(0x20));
+    ///     assert_eq!(scanner.resolve_va(&m, &data, 0x1000), Some(0x1020));
+    ///     Scan::Continue
+    /// });
+    /// ```
+    pub fn resolve(&self, matched: &MatchedPattern, data: &[u8]) -> Option<usize> {
+        let pattern = &self.patterns[matched.id().usize()];
+        resolve::follow(data, matched.target(), &pattern.resolve)
+    }
+
+    /// Like [`Hexpotter::resolve`], but returns the virtual address of the
+    /// final target for a buffer mapped at `base`.
+    pub fn resolve_va(&self, matched: &MatchedPattern, data: &[u8], base: u64) -> Option<u64> {
+        base.checked_add(self.resolve(matched, data)? as u64)
+    }
+
+    /// The anchor the engines search for to find candidates of a pattern.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexpotter::{Hexpotter, PatternId};
+    /// let scanner = Hexpotter::new(["00 00 00 ?? E8 4C"]);
+    ///
+    /// // `00` is far more common in code than `E8 4C`
+    /// let anchor = scanner.anchor(PatternId(0));
+    /// assert_eq!(anchor.offset(), 4);
+    /// assert_eq!(anchor.literals(), [vec![0xE8, 0x4C]]);
+    /// ```
+    pub fn anchor(&self, id: PatternId) -> &AnchorSet {
+        &self.anchors[id.usize()]
+    }
+
+    /// The engine the scanner was built with. Never [`EngineKind::Auto`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexpotter::{EngineKind, Hexpotter, SimdPath};
+    /// let scanner = Hexpotter::builder()
+    ///     .engine(EngineKind::Anchor)
+    ///     .build(["48 8B 05"])
+    ///     .unwrap();
+    ///
+    /// assert_eq!(scanner.engine(), EngineKind::Anchor);
+    /// assert_eq!(scanner.simd(), SimdPath::Scalar);
+    /// ```
+    pub fn engine(&self) -> EngineKind {
+        self.engine.kind()
+    }
+
+    /// The instruction set the engine scans with on this CPU.
+    pub fn simd(&self) -> SimdPath {
+        self.engine.simd()
+    }
+}
+
+/// Configures and compiles a [`Hexpotter`].
+#[derive(Clone, Debug, Default)]
+pub struct HexpotterBuilder {
+    dialect: Dialect,
+    frequencies: ByteFrequencies,
+    engine: EngineKind,
+}
+
+impl HexpotterBuilder {
+    /// A builder with the default dialect detection, engine and byte frequencies.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses pattern strings in the given [`Dialect`] instead of detecting it.
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Forces a search engine instead of choosing one from the CPU features,
+    /// e.g. for reproducible benchmarks. Every engine reports the same matches.
+    pub fn engine(mut self, engine: EngineKind) -> Self {
+        self.engine = engine;
+        self
+    }
+
+    /// Sets the byte frequency model anchors are chosen with. Defaults to
+    /// [`ByteFrequencies::x86`].
+    pub fn byte_frequencies(mut self, frequencies: ByteFrequencies) -> Self {
+        self.frequencies = frequencies;
+        self
+    }
+
+    /// Chooses anchors from the byte frequencies of `sample`, which should
+    /// resemble the data to be scanned.
+    pub fn sample(self, sample: &[u8]) -> Self {
+        self.byte_frequencies(ByteFrequencies::from_sample(sample))
+    }
+
+    /// Compiles pattern strings, returning every pattern that failed to
+    /// compile.
+    pub fn build<'s, I>(&self, patterns: I) -> Result<Hexpotter, Vec<PatternError>>
+    where
+        I: IntoIterator<Item = &'s str>,
+    {
+        let mut compiled = Vec::new();
+        let mut errors = Vec::new();
+
+        for (index, pattern) in patterns.into_iter().enumerate() {
+            match Pattern::parse_with(pattern, self.dialect) {
+                Ok(pattern) => compiled.push(pattern),
+                Err(error) => errors.push(error.with_pattern(index)),
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(self.build_patterns(compiled))
+    }
+
+    /// Compiles already parsed patterns. Pattern IDs follow the iteration
+    /// order.
+    pub fn build_patterns<I>(&self, patterns: I) -> Hexpotter
+    where
+        I: IntoIterator,
+        I::Item: Into<Pattern>,
+    {
+        let patterns: Vec<Pattern> = patterns.into_iter().map(Into::into).collect();
+        let anchors: Vec<AnchorSet> = patterns
+            .iter()
+            .map(|pattern| common::find_best_anchor(pattern, &self.frequencies))
+            .collect();
+
+        Hexpotter {
+            engine: self.select_engine(&patterns, &anchors),
+            patterns,
+            anchors,
+        }
+    }
+
+    fn select_engine(&self, patterns: &[Pattern], anchors: &[AnchorSet]) -> Box<dyn LookupEngine> {
+        match self.engine {
+            EngineKind::Auto if SimdPath::detect() != SimdPath::Scalar && Teddy::fits(anchors) => {
+                Box::new(Teddy::new(patterns, anchors))
+            }
+            EngineKind::Auto | EngineKind::Anchor => Box::new(Anchor::new(patterns, anchors)),
+            EngineKind::Teddy => Box::new(Teddy::new(patterns, anchors)),
+            EngineKind::Scalar => Box::new(Scalar::new(patterns, anchors)),
+        }
+    }
 }
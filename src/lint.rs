@@ -0,0 +1,202 @@
+//! Diagnostics for a pattern set, reported before it is compiled.
+
+use std::fmt::Display;
+
+use crate::{
+    engine::{ByteFrequencies, common},
+    pattern::{ByteClass, Pattern},
+};
+
+/// Anchors shorter than this many bytes are reported as [`LintKind::ShortAnchor`].
+const SHORT_ANCHOR_LEN: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Harmless, but worth tidying up.
+    Info,
+    /// Likely to slow scanning down or to be a mistake.
+    Warning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintKind {
+    /// No position accepts fewer than three bytes, so the pattern is anchored
+    /// on masked positions, such as nibbles, that match far more often.
+    NoFixedByte,
+    /// No position narrows the search at all, so the pattern is verified at
+    /// every offset of the input.
+    Unanchored,
+    /// The anchor the engines search for is only `len` bytes long, which
+    /// produces many candidates to verify.
+    ShortAnchor { len: usize },
+    /// The pattern matches exactly where an earlier one does. Target markers,
+    /// captures and resolution chains are not compared.
+    Duplicate { of: usize },
+    /// Every match of the pattern is also a match of another one.
+    Subsumed { by: usize },
+    /// The pattern ends with `count` wildcards that only require the data to
+    /// extend further.
+    TrailingWildcards { count: usize },
+}
+
+impl LintKind {
+    pub fn severity(&self) -> Severity {
+        match self {
+            LintKind::NoFixedByte => Severity::Warning,
+            LintKind::Unanchored => Severity::Warning,
+            LintKind::ShortAnchor { len: 1 } => Severity::Warning,
+            LintKind::ShortAnchor { .. } => Severity::Info,
+            LintKind::Duplicate { .. } => Severity::Warning,
+            LintKind::Subsumed { .. } => Severity::Info,
+            LintKind::TrailingWildcards { .. } => Severity::Info,
+        }
+    }
+}
+
+/// A diagnostic about one pattern of a set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lint {
+    pattern: usize,
+    kind: LintKind,
+}
+
+impl Lint {
+    /// Index of the pattern in the linted set.
+    pub fn pattern_index(&self) -> usize {
+        self.pattern
+    }
+
+    pub fn kind(&self) -> LintKind {
+        self.kind
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.kind.severity()
+    }
+}
+
+/// Inspects a pattern set and reports patterns that are poorly anchored,
+/// flood the prefilter, duplicate or subsume each other, or end with
+/// useless wildcards. Lints are ordered by pattern index.
+///
+/// # Example
+///
+/// ```rust
+/// # use hexpotter::{LintKind, Pattern, Severity, lint};
+/// let patterns: Vec<Pattern> = ["48 8B 05 ?? ??", "?? 4? ?5", "48 8B 05 ?? ??"]
+///     .iter()
+///     .map(|p| p.parse().unwrap())
+///     .collect();
+///
+/// let lints = lint(&patterns);
+/// assert_eq!(lints[0].kind(), LintKind::TrailingWildcards { count: 2 });
+/// assert_eq!(lints[1].kind(), LintKind::NoFixedByte);
+/// assert_eq!(lints[1].severity(), Severity::Warning);
+/// assert_eq!(lints[2].kind(), LintKind::Duplicate { of: 0 });
+/// ```
+pub fn lint(patterns: &[Pattern]) -> Vec<Lint> {
+    let classes: Vec<Vec<ByteClass>> = patterns
+        .iter()
+        .map(|p| (0..p.len()).map(|i| p.position_class(i)).collect())
+        .collect();
+
+    let mut lints = Vec::new();
+    for (index, pattern) in patterns.iter().enumerate() {
+        let mut push = |kind| {
+            lints.push(Lint {
+                pattern: index,
+                kind,
+            })
+        };
+
+        let anchor = common::find_best_anchor(pattern, &ByteFrequencies::default());
+        if !anchor.is_anchored() {
+            push(LintKind::Unanchored);
+        } else if classes[index].iter().all(|class| class.len() > 2) {
+            push(LintKind::NoFixedByte);
+        } else {
+            let len = anchor.literals[0].len();
+            if len < SHORT_ANCHOR_LEN && len < pattern.len() {
+                push(LintKind::ShortAnchor { len });
+            }
+        }
+
+        // duplicates are reported once, on the later pattern, and not as
+        // subsuming each other
+        let same =
+            |other: usize| classes[other] == classes[index] && patterns[other].gaps == pattern.gaps;
+        if let Some(of) = (0..index).find(|&other| same(other)) {
+            push(LintKind::Duplicate { of });
+        } else if let Some(by) = (0..patterns.len()).find(|&other| {
+            other != index
+                && !same(other)
+                && subsumes(&patterns[other], &classes[other], pattern, &classes[index])
+        }) {
+            push(LintKind::Subsumed { by });
+        }
+
+        let count = classes[index]
+            .iter()
+            .skip(pattern.required_len())
+            .rev()
+            .take_while(|class| class.len() == 256)
+            .count();
+        if count > 0 && count < pattern.len() {
+            push(LintKind::TrailingWildcards { count });
+        }
+    }
+    lints
+}
+
+/// Whether `general` matches wherever `specific` does, at the same start.
+/// Only patterns without variable jumps are compared.
+fn subsumes(
+    general: &Pattern,
+    general_classes: &[ByteClass],
+    specific: &Pattern,
+    specific_classes: &[ByteClass],
+) -> bool {
+    general.gaps.is_empty()
+        && specific.gaps.is_empty()
+        && general.len() <= specific.len()
+        && general_classes
+            .iter()
+            .zip(specific_classes)
+            .all(|(g, s)| s.is_subset(g))
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+impl Display for LintKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LintKind::NoFixedByte => write!(f, "no fixed byte to anchor the pattern"),
+            LintKind::Unanchored => write!(f, "pattern is verified at every offset"),
+            LintKind::ShortAnchor { len } => write!(f, "anchor is only {len} byte(s) long"),
+            LintKind::Duplicate { of } => write!(f, "duplicate of pattern #{of}"),
+            LintKind::Subsumed { by } => write!(f, "every match is also a match of pattern #{by}"),
+            LintKind::TrailingWildcards { count } => {
+                write!(f, "{count} trailing wildcard(s) add nothing")
+            }
+        }
+    }
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "pattern #{}: {}: {}",
+            self.pattern,
+            self.severity(),
+            self.kind
+        )
+    }
+}
@@ -1,6 +1,19 @@
-use std::fmt::Display;
+mod class;
+#[doc(hidden)]
+pub mod constant;
+mod parser;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use std::{
+    fmt::Display,
+    ops::{Range, RangeInclusive},
+    str::FromStr,
+};
+
+use crate::{capture::CaptureType, resolve::Relative};
+
+pub(crate) use class::ByteClass;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct PatternId(pub usize);
 
 impl PatternId {
@@ -9,7 +22,7 @@ impl PatternId {
     }
 
     pub fn usize(&self) -> usize {
-        self.0 as usize
+        self.0
     }
 }
 
@@ -18,3 +31,848 @@ impl Display for PatternId {
         write!(f, "#{}", self.0)
     }
 }
+
+/// A compiled hex pattern, stored as parallel value/mask tables.
+///
+/// A data byte `b` matches position `i` when `b & masks[i] == values[i]` and,
+/// if the position carries a byte class, the class contains `b`.
+///
+/// Patterns are either parsed from text, or built programmatically with
+/// [`Pattern::builder`]. [`Display`] renders a pattern in the native syntax,
+/// which parses back to an equal pattern.
+///
+/// # Example
+///
+/// ```rust
+/// # use hexpotter::Pattern;
+/// let built = Pattern::builder()
+///     .bytes(&[0x48, 0x8B])
+///     .wildcard()
+///     .nibble_hi(0x4)
+///     .masked(0x05, 0xC7)
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(built.to_string(), "48 8B ?? 4? 05&C7");
+/// assert_eq!(built.to_string().parse::<Pattern>().unwrap(), built);
+/// ```
+///
+/// There is no empty pattern: it would match at every offset, so parsing and
+/// building reject one with [`PatternErrorKind::EmptyPattern`].
+///
+/// ```compile_fail
+/// let empty = hexpotter::Pattern::default();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    pub(crate) values: Vec<u8>,
+    pub(crate) masks: Vec<u8>,
+    /// Positions restricted to a set of bytes that a single value/mask pair
+    /// cannot express, sorted by position.
+    pub(crate) classes: Vec<(usize, ByteClass)>,
+    /// Variable-length jumps between positions, sorted by position.
+    pub(crate) gaps: Vec<Gap>,
+    /// The position marked with `^`, reported by [`MatchedPattern::target`](crate::MatchedPattern::target).
+    /// May equal `len()` when the marker ends the pattern.
+    pub(crate) target: Option<usize>,
+    /// Named typed captures, sorted by position.
+    pub(crate) captures: Vec<CaptureSpec>,
+    /// Relative operands to follow from the match target, declared with `=>`.
+    pub(crate) resolve: Vec<Relative>,
+}
+
+/// A `<name:type>` capture covering `ty.size()` positions starting at `at`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CaptureSpec {
+    pub(crate) name: String,
+    pub(crate) at: usize,
+    pub(crate) ty: CaptureType,
+}
+
+/// Most bytes a single jump may skip, so a pattern's length and the distance
+/// between its anchor and start cannot overflow.
+pub const MAX_JUMP: usize = 1 << 16;
+
+/// A run of `min..=max` arbitrary bytes inserted before position `at`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Gap {
+    pub(crate) at: usize,
+    pub(crate) min: usize,
+    pub(crate) max: usize,
+}
+
+impl Pattern {
+    /// An empty pattern, only ever used as the start of a non-empty one.
+    pub(crate) fn new() -> Self {
+        Pattern {
+            values: Vec::new(),
+            masks: Vec::new(),
+            classes: Vec::new(),
+            gaps: Vec::new(),
+            target: None,
+            captures: Vec::new(),
+            resolve: Vec::new(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, value: u8, mask: u8) {
+        self.values.push(value & mask);
+        self.masks.push(mask);
+    }
+
+    /// Appends a position accepting any byte of `class`, which must not be empty.
+    ///
+    /// The position's value/mask pair is the class cover, so the class itself
+    /// is only kept when the cover accepts bytes outside of it.
+    pub(crate) fn push_class(&mut self, class: ByteClass) {
+        let (value, mask) = class.cover().expect("empty byte class");
+        if !class.is_masked() {
+            self.classes.push((self.values.len(), class));
+        }
+        self.push(value, mask);
+    }
+
+    /// Appends a capture, which matches any byte at each of its positions.
+    pub(crate) fn push_capture(&mut self, name: &str, ty: CaptureType) {
+        self.captures.push(CaptureSpec {
+            name: name.to_string(),
+            at: self.len(),
+            ty,
+        });
+        for _ in 0..ty.size() {
+            self.push(0x00, 0x00);
+        }
+    }
+
+    /// Appends a jump over `min..=max` arbitrary bytes.
+    ///
+    /// Fixed-length jumps become plain wildcards, and consecutive variable
+    /// jumps are merged into one. Fails without appending anything when the
+    /// merged jump would skip more than [`MAX_JUMP`] bytes.
+    pub(crate) fn push_gap(&mut self, min: usize, max: usize) -> Result<(), PatternErrorKind> {
+        if min == max {
+            for _ in 0..min {
+                self.push(0x00, 0x00);
+            }
+            return Ok(());
+        }
+
+        let at = self.values.len();
+        match self.gaps.last_mut() {
+            Some(gap) if gap.at == at => {
+                if gap.max + max > MAX_JUMP {
+                    return Err(PatternErrorKind::InvalidJump);
+                }
+                gap.min += min;
+                gap.max += max;
+            }
+            _ if max > MAX_JUMP => return Err(PatternErrorKind::InvalidJump),
+            _ => self.gaps.push(Gap { at, min, max }),
+        }
+        Ok(())
+    }
+
+    /// Starts building a pattern one position at a time.
+    pub fn builder() -> PatternBuilder {
+        PatternBuilder::default()
+    }
+
+    /// Parses a hex pattern such as `"48 8B ?? F? ?5"`, detecting its
+    /// [`Dialect`] automatically.
+    ///
+    /// Errors returned from this function always report pattern index `0`;
+    /// [`Hexpotter::try_new`](crate::Hexpotter::try_new) fills in the real index.
+    pub fn parse(pattern: &str) -> Result<Self, PatternError> {
+        Self::parse_with(pattern, Dialect::Auto)
+    }
+
+    /// Parses a hex pattern written in the given [`Dialect`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexpotter::{Dialect, Pattern};
+    /// let ida = Pattern::parse_with("48 8B ? ? 05", Dialect::Ida).unwrap();
+    /// let compact = Pattern::parse_with("488B????05", Dialect::Compact).unwrap();
+    /// let code = Pattern::parse_with(r"\x48\x8B\x00\x00\x05 xx??x", Dialect::Code).unwrap();
+    /// let cheat_engine = Pattern::parse_with("48 8B * * 05", Dialect::CheatEngine).unwrap();
+    ///
+    /// assert_eq!(ida, compact);
+    /// assert_eq!(ida, code);
+    /// assert_eq!(ida, cheat_engine);
+    /// ```
+    pub fn parse_with(pattern: &str, dialect: Dialect) -> Result<Self, PatternError> {
+        parser::parse(pattern, dialect)
+    }
+
+    /// Number of byte positions in this pattern, not counting the bytes
+    /// skipped by variable-length jumps.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Length of the shortest possible match.
+    pub fn min_len(&self) -> usize {
+        self.len() + self.gaps.iter().map(|gap| gap.min).sum::<usize>()
+    }
+
+    /// Length of the longest possible match.
+    pub fn max_len(&self) -> usize {
+        self.len() + self.gaps.iter().map(|gap| gap.max).sum::<usize>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Number of leading positions the target marker and captures refer to.
+    pub(crate) fn required_len(&self) -> usize {
+        let captures = self.captures.iter().map(|c| c.at + c.ty.size());
+        captures.chain(self.target).max().unwrap_or(0)
+    }
+
+    /// Drops every position from `len` on, with the jumps leading to them.
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.values.truncate(len);
+        self.masks.truncate(len);
+        self.classes.retain(|&(at, _)| at < len);
+        self.gaps.retain(|gap| gap.at < len);
+    }
+
+    /// Turns `position` into a wildcard.
+    pub(crate) fn clear(&mut self, position: usize) {
+        self.values[position] = 0x00;
+        self.masks[position] = 0x00;
+        self.classes.retain(|&(at, _)| at != position);
+    }
+
+    /// The positions of the `segment`-th run of bytes between jumps.
+    pub(crate) fn segment_range(&self, segment: usize) -> Range<usize> {
+        let start = if segment == 0 {
+            0
+        } else {
+            self.gaps[segment - 1].at
+        };
+        let end = self.gaps.get(segment).map_or(self.len(), |gap| gap.at);
+        start..end
+    }
+
+    /// Every byte accepted at `position`.
+    pub(crate) fn position_class(&self, position: usize) -> ByteClass {
+        match self.classes.binary_search_by_key(&position, |(at, _)| *at) {
+            Ok(i) => self.classes[i].1,
+            Err(_) => ByteClass::masked(self.values[position], self.masks[position]),
+        }
+    }
+
+    fn matches_position(&self, position: usize, b: u8) -> bool {
+        b & self.masks[position] == self.values[position]
+            && self
+                .classes
+                .binary_search_by_key(&position, |(at, _)| *at)
+                .map_or(true, |i| self.classes[i].1.contains(b))
+    }
+
+    /// Finds where each position of a match spanning `start..end` lies in
+    /// `data`, choosing the shortest jumps first.
+    pub(crate) fn locate(&self, data: &[u8], start: usize, end: usize) -> Option<Vec<usize>> {
+        let mut jumps = Jumps::new(self, start..=start, data.len());
+        if self.resolve_jumps(data, start, &mut jumps)? != end {
+            return None;
+        }
+
+        let mut offsets = Vec::with_capacity(self.len());
+        for (segment, &seg_start) in jumps.starts().iter().enumerate() {
+            let range = self.segment_range(segment);
+            offsets.extend(
+                range
+                    .clone()
+                    .map(|position| seg_start + position - range.start),
+            );
+        }
+        Some(offsets)
+    }
+
+    /// Matches the pattern from `start`, resolving its jumps shortest-first
+    /// from the first segment, and returns where the match ends.
+    /// [`Jumps::starts`] then holds where each segment starts.
+    ///
+    /// A segment start that leads nowhere is recorded in `jumps` and never
+    /// tried again, so the work is bounded by the number of segment starts
+    /// rather than by the product of the jump widths.
+    pub(crate) fn resolve_jumps(
+        &self,
+        data: &[u8],
+        start: usize,
+        jumps: &mut Jumps,
+    ) -> Option<usize> {
+        jumps.starts[0] = start;
+        self.resolve_segment(data, 0, jumps)
+    }
+
+    fn resolve_segment(&self, data: &[u8], segment: usize, jumps: &mut Jumps) -> Option<usize> {
+        let seg_start = jumps.starts[segment];
+        if jumps.is_dead_end(segment, seg_start) {
+            return None;
+        }
+
+        let range = self.segment_range(segment);
+        let seg_end = seg_start + range.len();
+        let matches = data.get(seg_start..seg_end).is_some_and(|bytes| {
+            range
+                .zip(bytes)
+                .all(|(position, &b)| self.matches_position(position, b))
+        });
+        if matches {
+            let Some(gap) = self.gaps.get(segment) else {
+                return Some(seg_end);
+            };
+            let next_len = self.segment_range(segment + 1).len();
+            let mut from = seg_end + gap.min;
+            let to = (seg_end + gap.max).min(data.len().saturating_sub(next_len));
+            while let Some(next_start) = jumps.next_open(segment + 1, from, to) {
+                jumps.starts[segment + 1] = next_start;
+                if let Some(end) = self.resolve_segment(data, segment + 1, jumps) {
+                    return Some(end);
+                }
+                from = next_start + 1;
+            }
+        }
+
+        jumps.mark_dead_end(segment, seg_start);
+        None
+    }
+}
+
+/// State shared by [`Pattern::resolve_jumps`] over matches starting within
+/// a window: where the segments of the last match start, and the segment
+/// starts known to lead nowhere.
+pub(crate) struct Jumps {
+    starts: Vec<usize>,
+    /// Earliest position a segment can start at.
+    base: usize,
+    /// Number of positions from `base` on a segment can start at.
+    width: usize,
+    /// One bit per segment and position, allocated on the first dead end.
+    dead_ends: Vec<u64>,
+}
+
+impl Jumps {
+    /// Prepares the searches for matches of `pattern` starting in `starts`,
+    /// within data of `len` bytes.
+    pub(crate) fn new(pattern: &Pattern, starts: RangeInclusive<usize>, len: usize) -> Self {
+        let base = *starts.start();
+        let width = starts.end() - base + pattern.max_len();
+        Jumps {
+            starts: vec![0; pattern.gaps.len() + 1],
+            base,
+            width: width.min(len.saturating_sub(base)) + 1,
+            dead_ends: Vec::new(),
+        }
+    }
+
+    /// Where each segment of the last match found starts.
+    pub(crate) fn starts(&self) -> &[usize] {
+        &self.starts
+    }
+
+    fn bit(&self, segment: usize, position: usize) -> Option<usize> {
+        let offset = position.checked_sub(self.base)?;
+        (offset < self.width).then_some(segment * self.width + offset)
+    }
+
+    fn is_dead_end(&self, segment: usize, position: usize) -> bool {
+        self.bit(segment, position).is_some_and(|bit| {
+            self.dead_ends
+                .get(bit / 64)
+                .is_some_and(|word| word & (1 << (bit % 64)) != 0)
+        })
+    }
+
+    /// The first position in `from..=to` where `segment` may start, skipping
+    /// the known dead ends a word at a time.
+    fn next_open(&self, segment: usize, from: usize, to: usize) -> Option<usize> {
+        if from > to {
+            return None;
+        }
+        let (Some(first), Some(last)) = (self.bit(segment, from), self.bit(segment, to)) else {
+            return Some(from);
+        };
+        if self.dead_ends.is_empty() {
+            return Some(from);
+        }
+
+        let mut bit = first;
+        while bit <= last {
+            let open = !self.dead_ends[bit / 64] >> (bit % 64);
+            if open != 0 {
+                let bit = bit + open.trailing_zeros() as usize;
+                return (bit <= last).then_some(from + (bit - first));
+            }
+            bit = (bit / 64 + 1) * 64;
+        }
+        None
+    }
+
+    fn mark_dead_end(&mut self, segment: usize, position: usize) {
+        let Some(bit) = self.bit(segment, position) else {
+            return;
+        };
+        if self.dead_ends.is_empty() {
+            self.dead_ends = vec![0; (self.starts.len() * self.width).div_ceil(64)];
+        }
+        self.dead_ends[bit / 64] |= 1 << (bit % 64);
+    }
+}
+
+/// Builds a [`Pattern`] programmatically, without going through its text form.
+///
+/// Each method appends to the pattern; [`build`](Self::build) checks it is
+/// not empty and that no variable-length jump sits at either end.
+#[derive(Debug, Clone)]
+pub struct PatternBuilder {
+    pattern: Pattern,
+    /// Position of the first jump longer than [`MAX_JUMP`].
+    invalid_jump: Option<usize>,
+}
+
+impl Default for PatternBuilder {
+    fn default() -> Self {
+        PatternBuilder {
+            pattern: Pattern::new(),
+            invalid_jump: None,
+        }
+    }
+}
+
+impl PatternBuilder {
+    /// Appends an exact byte.
+    pub fn byte(mut self, value: u8) -> Self {
+        self.pattern.push(value, 0xFF);
+        self
+    }
+
+    /// Appends a run of exact bytes.
+    pub fn bytes(mut self, values: &[u8]) -> Self {
+        for &value in values {
+            self.pattern.push(value, 0xFF);
+        }
+        self
+    }
+
+    /// Appends a position matching any byte (`??`).
+    pub fn wildcard(self) -> Self {
+        self.masked(0x00, 0x00)
+    }
+
+    /// Appends `count` positions matching any byte.
+    pub fn wildcards(mut self, count: usize) -> Self {
+        for _ in 0..count {
+            self.pattern.push(0x00, 0x00);
+        }
+        self
+    }
+
+    /// Appends a byte whose high nibble is `hi` (`X?`). Only the low four
+    /// bits of `hi` are used.
+    pub fn nibble_hi(self, hi: u8) -> Self {
+        self.masked(hi << 4, 0xF0)
+    }
+
+    /// Appends a byte whose low nibble is `lo` (`?X`). Only the low four
+    /// bits of `lo` are used.
+    pub fn nibble_lo(self, lo: u8) -> Self {
+        self.masked(lo, 0x0F)
+    }
+
+    /// Appends a byte compared only on the bits set in `mask` (`XX&MM`).
+    pub fn masked(mut self, value: u8, mask: u8) -> Self {
+        self.pattern.push(value, mask);
+        self
+    }
+
+    /// Appends a position matching any of `values` (`[XX YY]`).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `values` is empty.
+    pub fn any_of(mut self, values: &[u8]) -> Self {
+        let mut class = ByteClass::new();
+        values.iter().for_each(|&b| class.insert(b));
+        self.pattern.push_class(class);
+        self
+    }
+
+    /// Appends a position matching any byte except `values` (`![XX YY]`).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `values` contains all 256 bytes.
+    pub fn none_of(mut self, values: &[u8]) -> Self {
+        let mut class = ByteClass::new();
+        values.iter().for_each(|&b| class.insert(b));
+        self.pattern.push_class(class.negate());
+        self
+    }
+
+    /// Appends a jump over `min..=max` arbitrary bytes (`[min-max]`).
+    /// [`build`](Self::build) fails with [`PatternErrorKind::InvalidJump`]
+    /// when `max`, or the jump it is merged with, exceeds [`MAX_JUMP`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min > max`.
+    pub fn jump(mut self, min: usize, max: usize) -> Self {
+        assert!(min <= max, "jump bounds are reversed");
+        let at = self.pattern.len();
+        if self.pattern.push_gap(min, max).is_err() {
+            self.invalid_jump.get_or_insert(at);
+        }
+        self
+    }
+
+    /// Marks the next position as the match target (`^`).
+    pub fn target(mut self) -> Self {
+        self.pattern.target = Some(self.pattern.len());
+        self
+    }
+
+    /// Appends a named capture (`<name:type>`).
+    pub fn capture(mut self, name: &str, ty: CaptureType) -> Self {
+        self.pattern.push_capture(name, ty);
+        self
+    }
+
+    /// Appends a step to the resolution chain (`=> step`).
+    pub fn follow(mut self, step: Relative) -> Self {
+        self.pattern.resolve.push(step);
+        self
+    }
+
+    pub fn build(self) -> Result<Pattern, PatternError> {
+        let pattern = self.pattern;
+        let error = |token, kind| PatternError {
+            pattern: 0,
+            token,
+            column: 0,
+            kind,
+        };
+
+        if pattern.is_empty() {
+            return Err(error(0, PatternErrorKind::EmptyPattern));
+        }
+        if let Some(at) = self.invalid_jump {
+            return Err(error(at, PatternErrorKind::InvalidJump));
+        }
+        if let Some(gap) = pattern
+            .gaps
+            .iter()
+            .find(|gap| gap.at == 0 || gap.at == pattern.len())
+        {
+            return Err(error(gap.at, PatternErrorKind::MisplacedJump));
+        }
+
+        let mut names: Vec<&str> = pattern.captures.iter().map(|c| c.name.as_str()).collect();
+        names.sort_unstable();
+        if let Some(pair) = names.windows(2).find(|pair| pair[0] == pair[1]) {
+            let at = pattern
+                .captures
+                .iter()
+                .rfind(|c| c.name == pair[0])
+                .map_or(0, |c| c.at);
+            return Err(error(at, PatternErrorKind::DuplicateCapture));
+        }
+
+        Ok(pattern)
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = PatternError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Pattern::parse(s)
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut tokens = Vec::with_capacity(self.len());
+        let mut gaps = self.gaps.iter().peekable();
+        let mut captures = self.captures.iter().peekable();
+
+        let mut position = 0;
+        while position < self.len() {
+            if let Some(gap) = gaps.next_if(|gap| gap.at == position) {
+                tokens.push(format!("[{}-{}]", gap.min, gap.max));
+            }
+
+            let marker = if self.target == Some(position) {
+                "^"
+            } else {
+                ""
+            };
+            if let Some(capture) = captures.next_if(|c| c.at == position) {
+                tokens.push(format!("{marker}<{}:{}>", capture.name, capture.ty));
+                position += capture.ty.size();
+            } else {
+                let token = match self.classes.binary_search_by_key(&position, |(at, _)| *at) {
+                    Ok(i) => format_class(&self.classes[i].1),
+                    Err(_) => format_masked(self.values[position], self.masks[position]),
+                };
+                tokens.push(format!("{marker}{token}"));
+                position += 1;
+            }
+        }
+        if self.target == Some(self.len()) {
+            tokens.push("^".to_string());
+        }
+
+        for step in &self.resolve {
+            tokens.push(format!("=> {step}"));
+        }
+
+        write!(f, "{}", tokens.join(" "))
+    }
+}
+
+/// Formats a value/mask pair with the shortest native token.
+fn format_masked(value: u8, mask: u8) -> String {
+    match mask {
+        0xFF => format!("{value:02X}"),
+        0xF0 => format!("{:X}?", value >> 4),
+        0x0F => format!("?{:X}", value & 0x0F),
+        0x00 => "??".to_string(),
+        _ => format!("{value:02X}&{mask:02X}"),
+    }
+}
+
+/// Formats a byte class as a single native token.
+fn format_class(class: &ByteClass) -> String {
+    if let Some((value, mask)) = class.cover().filter(|_| class.is_masked()) {
+        return format_masked(value, mask);
+    }
+    if class.len() > 128 {
+        return format!("!{}", format_class(&class.negate()));
+    }
+
+    let mut items = Vec::new();
+    let members: Vec<u8> = class.iter().collect();
+    let mut i = 0;
+    while i < members.len() {
+        let mut j = i;
+        while j + 1 < members.len() && members[j + 1] == members[j] + 1 {
+            j += 1;
+        }
+        let (lo, hi) = (format!("{:02X}", members[i]), format!("{:02X}", members[j]));
+        // a range with decimal-looking bounds would read back as a jump
+        let decimal = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+        if j - i >= 2 && !(decimal(&lo) && decimal(&hi)) {
+            items.push(format!("{lo}-{hi}"));
+            i = j + 1;
+        } else {
+            items.push(lo);
+            i += 1;
+        }
+    }
+    format!("[{}]", items.join(" "))
+}
+
+/// The textual signature formats accepted by [`Pattern::parse_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    /// Detects the dialect from the shape of the pattern: code-style when it
+    /// starts with `\x`, compact when it is a single token of more than one
+    /// byte holding at least one hex digit, [`Dialect::Native`] otherwise.
+    #[default]
+    Auto,
+    /// Hexpotter's own syntax, shared with x64dbg: `48 8B ?? F? ?5`. The IDA
+    /// `?` and Cheat Engine `*` wildcards are accepted as well.
+    ///
+    /// A position may also be a byte class, either a bracketed list of bytes
+    /// and ranges (`[40-4F]`, `[48 4C 49]`) or an alternation (`(48|4C)`).
+    ///
+    /// YARA-style jumps skip a bounded number of arbitrary bytes, at most
+    /// [`MAX_JUMP`]: `[4]` skips exactly four, `[2-16]` between two and
+    /// sixteen. As in YARA, a bracket holding only decimal numbers is always
+    /// a jump, so a class such as `[40-45]` must be listed as
+    /// `[40 41 42 43 44 45]` instead.
+    ///
+    /// Any bit mask can be applied to a byte with `8B&C7` or `8B:C7`, which
+    /// only compare the bits set in `C7`, and bit patterns such as
+    /// `0b01??_?101` spell out a byte one bit at a time.
+    ///
+    /// Any single position can be negated with `!`: `!00` matches every byte
+    /// but zero, `![CC 90]` anything but padding.
+    ///
+    /// Prefixing a token with `^` (or writing a lone `^` before it) marks the
+    /// byte whose offset is reported by [`MatchedPattern::target`](crate::MatchedPattern::target),
+    /// as in `E8 ^?? ?? ?? ??`.
+    ///
+    /// Captures such as `<disp:i32le>` match any bytes and are decoded by
+    /// [`Hexpotter::captures`](crate::Hexpotter::captures). The types are
+    /// `u8`, `i8`, `u16`, `i16`, `u32`, `i32`, `u64` and `i64`, with an
+    /// optional `le` or `be` suffix (little-endian by default).
+    ///
+    /// String literals expand to their bytes: `"GetProcAddress"` as UTF-8,
+    /// `u"Kernel32"` as UTF-16LE and `i"error"` matching ASCII letters in any
+    /// case. The prefixes combine (`iu"error"`), and `\"`, `\\`, `\n`, `\r`,
+    /// `\t`, `\0` and `\xNN` escapes are understood.
+    ///
+    /// A pattern may end with a chain of relative operands to follow from the
+    /// match target, such as `E8 ?? ?? ?? ?? => call => jmp`, resolved by
+    /// [`Hexpotter::resolve`](crate::Hexpotter::resolve). See
+    /// [`Relative`](crate::resolve::Relative) for the available steps.
+    Native,
+    /// IDA style: `48 8B ? ? 05`. Only whole-byte wildcards (`?` or `??`).
+    Ida,
+    /// Cheat Engine AOB style: `48 8B * * 05`, also accepting `?` wildcards.
+    CheatEngine,
+    /// Hex digits with no separators: `488B????05`.
+    Compact,
+    /// Code-style escaped bytes followed by an optional mask, where `x` keeps
+    /// a byte and `?` ignores it: `\x48\x8B\x00\x00\x05 xx??x`.
+    Code,
+}
+
+/// The reason a pattern string failed to compile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatternErrorKind {
+    /// A character that is neither a hex digit nor a wildcard.
+    InvalidHexDigit(char),
+    /// A character in a `0b` bit pattern that is not `0`, `1`, `?` or `_`.
+    InvalidBinaryDigit(char),
+    /// A wildcard that is not valid in the pattern's dialect.
+    InvalidWildcard,
+    /// The pattern contains no tokens.
+    EmptyPattern,
+    /// A token longer than a single byte.
+    TokenTooLong,
+    /// A byte with a missing hex digit, such as a trailing digit in a compact
+    /// pattern or a bare `\x` in a code-style pattern.
+    IncompleteByte,
+    /// A code-style byte that does not start with `\x`, or an unknown escape
+    /// sequence in a string literal.
+    InvalidEscape,
+    /// A code-style mask whose length differs from the number of bytes.
+    MaskLengthMismatch,
+    /// A `[` or `(` group without its closing bracket.
+    UnclosedGroup,
+    /// A `[]` class or `()` alternation without any byte.
+    EmptyGroup,
+    /// A class range such as `[4F-40]` whose bounds are reversed or not
+    /// plain bytes.
+    InvalidRange,
+    /// An alternative longer than a single byte, such as `(48 8B|4C)`.
+    UnsupportedAlternation,
+    /// A jump such as `[16-2]` whose bounds are reversed or missing, or
+    /// that skips more than [`MAX_JUMP`] bytes.
+    InvalidJump,
+    /// A variable-length jump at the start or end of a pattern.
+    MisplacedJump,
+    /// More than one `^` target marker.
+    DuplicateMarker,
+    /// A capture that is not of the form `<name:type>` with a known type.
+    InvalidCapture,
+    /// Two captures with the same name.
+    DuplicateCapture,
+    /// A string literal without its closing quote.
+    UnterminatedString,
+    /// A string literal prefix other than `u`, `i` or both.
+    InvalidStringPrefix,
+    /// A `!` negation of something other than a single position, or of a
+    /// wildcard that every byte matches.
+    InvalidNegation,
+    /// A `=>` resolution step that is not `call`, `jmp`, `jmp8`, `jcc`, `rip`
+    /// or `rel(disp, size, len)`.
+    InvalidResolveStep,
+}
+
+/// An error produced while compiling a pattern string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternError {
+    pub(crate) pattern: usize,
+    pub(crate) token: usize,
+    pub(crate) column: usize,
+    pub(crate) kind: PatternErrorKind,
+}
+
+impl PatternError {
+    /// Index of the offending pattern in the input passed to the scanner.
+    pub fn pattern_index(&self) -> usize {
+        self.pattern
+    }
+
+    /// Index of the offending whitespace-separated token within the pattern.
+    pub fn token_index(&self) -> usize {
+        self.token
+    }
+
+    /// Byte offset of the offending character within the pattern string.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn kind(&self) -> PatternErrorKind {
+        self.kind
+    }
+
+    pub(crate) fn with_pattern(mut self, pattern: usize) -> Self {
+        self.pattern = pattern;
+        self
+    }
+
+    pub(crate) fn with_column(mut self, column: usize) -> Self {
+        self.column = column;
+        self
+    }
+}
+
+impl Display for PatternErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PatternErrorKind::InvalidHexDigit(c) => write!(f, "invalid hex digit {c:?}"),
+            PatternErrorKind::InvalidBinaryDigit(c) => write!(f, "invalid binary digit {c:?}"),
+            PatternErrorKind::InvalidWildcard => write!(f, "invalid wildcard"),
+            PatternErrorKind::EmptyPattern => write!(f, "empty pattern"),
+            PatternErrorKind::TokenTooLong => write!(f, "token is longer than one byte"),
+            PatternErrorKind::IncompleteByte => write!(f, "incomplete byte"),
+            PatternErrorKind::InvalidEscape => write!(f, "invalid escape"),
+            PatternErrorKind::MaskLengthMismatch => {
+                write!(f, "mask length does not match the number of bytes")
+            }
+            PatternErrorKind::UnclosedGroup => write!(f, "unclosed group"),
+            PatternErrorKind::EmptyGroup => write!(f, "empty group"),
+            PatternErrorKind::InvalidRange => write!(f, "invalid byte range"),
+            PatternErrorKind::UnsupportedAlternation => {
+                write!(f, "alternatives must be a single byte")
+            }
+            PatternErrorKind::InvalidJump => write!(f, "invalid jump"),
+            PatternErrorKind::MisplacedJump => {
+                write!(f, "variable jumps must be surrounded by bytes")
+            }
+            PatternErrorKind::DuplicateMarker => write!(f, "duplicate target marker"),
+            PatternErrorKind::InvalidCapture => write!(f, "invalid capture"),
+            PatternErrorKind::DuplicateCapture => write!(f, "duplicate capture name"),
+            PatternErrorKind::UnterminatedString => write!(f, "unterminated string"),
+            PatternErrorKind::InvalidStringPrefix => write!(f, "invalid string prefix"),
+            PatternErrorKind::InvalidNegation => write!(f, "invalid negation"),
+            PatternErrorKind::InvalidResolveStep => write!(f, "invalid resolution step"),
+        }
+    }
+}
+
+impl Display for PatternError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "pattern {}, token {} (column {}): {}",
+            PatternId(self.pattern),
+            self.token,
+            self.column,
+            self.kind
+        )
+    }
+}
+
+impl std::error::Error for PatternError {}
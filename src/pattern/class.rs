@@ -0,0 +1,90 @@
+/// A set of byte values accepted at a single pattern position.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub(crate) struct ByteClass([u64; 4]);
+
+impl ByteClass {
+    pub(crate) fn new() -> Self {
+        ByteClass([0; 4])
+    }
+
+    /// Every byte `b` with `b & mask == value`.
+    pub(crate) fn masked(value: u8, mask: u8) -> Self {
+        let mut class = ByteClass::new();
+        for b in 0..=255u8 {
+            if b & mask == value {
+                class.insert(b);
+            }
+        }
+        class
+    }
+
+    #[inline(always)]
+    pub(crate) fn contains(&self, b: u8) -> bool {
+        self.0[(b >> 6) as usize] & (1 << (b & 0x3F)) != 0
+    }
+
+    pub(crate) fn insert(&mut self, b: u8) {
+        self.0[(b >> 6) as usize] |= 1 << (b & 0x3F);
+    }
+
+    pub(crate) fn insert_range(&mut self, lo: u8, hi: u8) {
+        for b in lo..=hi {
+            self.insert(b);
+        }
+    }
+
+    pub(crate) fn union(&mut self, other: &ByteClass) {
+        for (a, b) in self.0.iter_mut().zip(other.0) {
+            *a |= b;
+        }
+    }
+
+    /// Whether every byte of `self` is also in `other`.
+    pub(crate) fn is_subset(&self, other: &ByteClass) -> bool {
+        self.0.iter().zip(other.0).all(|(a, b)| a & !b == 0)
+    }
+
+    /// Every byte not in the class.
+    pub(crate) fn negate(&self) -> Self {
+        ByteClass(self.0.map(|w| !w))
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.0.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0 == [0; 4]
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..=255u8).filter(|&b| self.contains(b))
+    }
+
+    /// The tightest value/mask pair accepting every byte of the class: the
+    /// mask keeps only the bits shared by all members.
+    ///
+    /// Returns `None` for an empty class.
+    pub(crate) fn cover(&self) -> Option<(u8, u8)> {
+        let mut members = self.iter();
+        let first = members.next()?;
+        let differing = members.fold(0, |acc, b| acc | (b ^ first));
+        let mask = !differing;
+        Some((first & mask, mask))
+    }
+
+    /// Whether the class accepts exactly the bytes of its [`cover`](Self::cover),
+    /// in which case the value/mask pair alone is enough to match it.
+    pub(crate) fn is_masked(&self) -> bool {
+        self.cover()
+            .is_some_and(|(_, mask)| self.len() == 1 << mask.count_zeros())
+    }
+}
+
+impl std::fmt::Debug for ByteClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set()
+            .entries(self.iter().map(|b| format!("{b:02X}")))
+            .finish()
+    }
+}
@@ -0,0 +1,209 @@
+//! Compile-time pattern parsing backing the [`pattern!`](crate::pattern!) macro.
+//!
+//! Only the plain byte syntax is understood here: exact bytes, `??`/`?`/`*`
+//! wildcards, nibble wildcards, `XX&MM` masks and the `^` target marker.
+//! Anything else is rejected at compile time and must go through
+//! [`Pattern::parse`](crate::Pattern::parse) instead.
+
+use crate::pattern::Pattern;
+
+/// A pattern parsed at compile time by [`pattern!`](crate::pattern!), stored
+/// as static value/mask tables.
+///
+/// Converting it into a [`Pattern`] copies the tables without reparsing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StaticPattern {
+    values: &'static [u8],
+    masks: &'static [u8],
+    target: Option<usize>,
+}
+
+impl StaticPattern {
+    #[doc(hidden)]
+    pub const fn from_tables(
+        values: &'static [u8],
+        masks: &'static [u8],
+        target: Option<usize>,
+    ) -> Self {
+        StaticPattern {
+            values,
+            masks,
+            target,
+        }
+    }
+
+    pub const fn values(&self) -> &'static [u8] {
+        self.values
+    }
+
+    pub const fn masks(&self) -> &'static [u8] {
+        self.masks
+    }
+
+    pub const fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl From<StaticPattern> for Pattern {
+    fn from(pattern: StaticPattern) -> Self {
+        Pattern {
+            values: pattern.values.to_vec(),
+            masks: pattern.masks.to_vec(),
+            target: pattern.target,
+            ..Pattern::new()
+        }
+    }
+}
+
+/// Parses a pattern at compile time into a [`StaticPattern`].
+///
+/// Syntax errors become compile errors at the macro call site. Only exact
+/// bytes, `??`/`?`/`*` wildcards, nibble wildcards (`4?`, `?5`), `XX&MM`
+/// masks and the `^` target marker are supported; classes, jumps, strings
+/// and captures need [`Pattern::parse`](crate::Pattern::parse).
+///
+/// # Example
+///
+/// ```rust
+/// use hexpotter::{Hexpotter, StaticPattern, pattern};
+///
+/// const LOAD: StaticPattern = pattern!("48 8B ?? 05");
+/// const CALL: StaticPattern = pattern!("E8 ^?? ?? ?? ??");
+///
+/// let scanner = Hexpotter::from_patterns([LOAD, CALL]);
+/// ```
+///
+/// ```rust,compile_fail
+/// // `4G` is not a hex byte
+/// const BROKEN: hexpotter::StaticPattern = hexpotter::pattern!("48 4G");
+/// ```
+#[macro_export]
+macro_rules! pattern {
+    ($pattern:literal) => {{
+        const LEN: usize = $crate::pattern::constant::len($pattern);
+        const TABLES: ([u8; LEN], [u8; LEN], Option<usize>) =
+            $crate::pattern::constant::parse::<LEN>($pattern);
+        const PATTERN: $crate::StaticPattern =
+            $crate::StaticPattern::from_tables(&TABLES.0, &TABLES.1, TABLES.2);
+        PATTERN
+    }};
+}
+
+/// Returns the bounds of the next whitespace-separated token at or after
+/// `from`, or an empty range at the end of input.
+const fn next_token(bytes: &[u8], from: usize) -> (usize, usize) {
+    let mut start = from;
+    while start < bytes.len() && bytes[start].is_ascii_whitespace() {
+        start += 1;
+    }
+    let mut end = start;
+    while end < bytes.len() && !bytes[end].is_ascii_whitespace() {
+        end += 1;
+    }
+    (start, end)
+}
+
+/// Number of positions in `pattern`.
+pub const fn len(pattern: &str) -> usize {
+    let bytes = pattern.as_bytes();
+    let mut count = 0;
+    let mut at = 0;
+    loop {
+        let (start, end) = next_token(bytes, at);
+        if start == end {
+            break;
+        }
+        if !(end - start == 1 && bytes[start] == b'^') {
+            count += 1;
+        }
+        at = end;
+    }
+    if count == 0 {
+        panic!("pattern!: empty pattern");
+    }
+    count
+}
+
+/// Parses `pattern`, which must have exactly `N` positions, into its value
+/// and mask tables and target position.
+pub const fn parse<const N: usize>(pattern: &str) -> ([u8; N], [u8; N], Option<usize>) {
+    let bytes = pattern.as_bytes();
+    let mut values = [0u8; N];
+    let mut masks = [0u8; N];
+    let mut target = None;
+
+    let mut position = 0;
+    let mut at = 0;
+    loop {
+        let (mut start, end) = next_token(bytes, at);
+        if start == end {
+            break;
+        }
+        at = end;
+
+        if bytes[start] == b'^' {
+            if target.is_some() {
+                panic!("pattern!: duplicate target marker");
+            }
+            target = Some(position);
+            start += 1;
+            if start == end {
+                continue;
+            }
+        }
+
+        let (value, mask) = token(bytes, start, end);
+        values[position] = value & mask;
+        masks[position] = mask;
+        position += 1;
+    }
+
+    (values, masks, target)
+}
+
+const fn is_wildcard(c: u8) -> bool {
+    c == b'?' || c == b'*'
+}
+
+const fn hex(c: u8) -> u8 {
+    match c {
+        b'0'..=b'9' => c - b'0',
+        b'a'..=b'f' => c - b'a' + 10,
+        b'A'..=b'F' => c - b'A' + 10,
+        _ => panic!("pattern!: invalid hex digit"),
+    }
+}
+
+const fn token(bytes: &[u8], start: usize, end: usize) -> (u8, u8) {
+    match bytes[start] {
+        b'[' | b'(' | b'<' | b'"' | b'!' | b'=' => {
+            panic!("pattern!: unsupported syntax, use Pattern::parse at runtime")
+        }
+        _ => {}
+    }
+
+    match end - start {
+        1 if is_wildcard(bytes[start]) => (0x00, 0x00),
+        1 => (hex(bytes[start]), 0xFF),
+        2 => {
+            let (a, b) = (bytes[start], bytes[start + 1]);
+            match (is_wildcard(a), is_wildcard(b)) {
+                (true, true) => (0x00, 0x00),
+                (false, true) => (hex(a) << 4, 0xF0),
+                (true, false) => (hex(b), 0x0F),
+                (false, false) => (hex(a) << 4 | hex(b), 0xFF),
+            }
+        }
+        5 if bytes[start + 2] == b'&' || bytes[start + 2] == b':' => {
+            let value = hex(bytes[start]) << 4 | hex(bytes[start + 1]);
+            let mask = hex(bytes[start + 3]) << 4 | hex(bytes[start + 4]);
+            (value, mask)
+        }
+        _ => panic!("pattern!: token is longer than one byte"),
+    }
+}
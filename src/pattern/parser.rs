@@ -0,0 +1,703 @@
+use crate::{
+    capture::CaptureType,
+    pattern::{ByteClass, Dialect, MAX_JUMP, Pattern, PatternError, PatternErrorKind},
+    resolve::Relative,
+};
+
+/// Splits `pattern` on ASCII whitespace, yielding each token with its byte column.
+///
+/// Whitespace inside `[...]` and `(...)` groups and `"..."` strings does not
+/// split a token, so `[48 4C]` is yielded whole. An unclosed group or string
+/// extends to the end of input.
+pub(crate) fn tokens(pattern: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut rest = 0;
+    std::iter::from_fn(move || {
+        let bytes = pattern.as_bytes();
+        let start = rest
+            + bytes[rest..]
+                .iter()
+                .position(|b| !b.is_ascii_whitespace())?;
+
+        let mut depth = 0usize;
+        let mut quoted = false;
+        let mut end = start;
+        while end < bytes.len() {
+            match bytes[end] {
+                b'\\' if quoted => end += 1,
+                b'"' => quoted = !quoted,
+                _ if quoted => {}
+                b'[' | b'(' => depth += 1,
+                b']' | b')' => depth = depth.saturating_sub(1),
+                b if b.is_ascii_whitespace() && depth == 0 => break,
+                _ => {}
+            }
+            end += 1;
+        }
+        let end = end.min(bytes.len());
+
+        rest = end;
+        Some((start, &pattern[start..end]))
+    })
+}
+
+/// Per-dialect rules for a single byte token.
+struct Syntax {
+    /// Characters accepted as a wildcard digit.
+    wildcards: &'static [u8],
+    /// Whether half-byte wildcards such as `4?` are accepted.
+    nibbles: bool,
+    /// Whether a lone wildcard character stands for a whole byte.
+    single_wildcard: bool,
+    /// Whether the native extensions (classes, jumps, bit masks) are accepted.
+    extended: bool,
+}
+
+impl Syntax {
+    fn of(dialect: Dialect) -> Self {
+        match dialect {
+            Dialect::Ida => Syntax {
+                wildcards: b"?",
+                nibbles: false,
+                single_wildcard: true,
+                extended: false,
+            },
+            Dialect::CheatEngine => Syntax {
+                wildcards: b"*?",
+                nibbles: true,
+                single_wildcard: true,
+                extended: false,
+            },
+            Dialect::Compact => Syntax {
+                wildcards: b"?",
+                nibbles: true,
+                single_wildcard: false,
+                extended: false,
+            },
+            _ => Syntax {
+                wildcards: b"?*",
+                nibbles: true,
+                single_wildcard: true,
+                extended: true,
+            },
+        }
+    }
+
+    fn is_wildcard(&self, c: u8) -> bool {
+        self.wildcards.contains(&c)
+    }
+}
+
+/// An error inside a token: the byte offset within the token and its kind.
+type TokenError = (usize, PatternErrorKind);
+
+pub(crate) fn parse(pattern: &str, dialect: Dialect) -> Result<Pattern, PatternError> {
+    let dialect = match dialect {
+        Dialect::Auto => detect_dialect(pattern),
+        dialect => dialect,
+    };
+
+    let pattern = match dialect {
+        Dialect::Compact => parse_compact(pattern)?,
+        Dialect::Code => parse_code(pattern)?,
+        _ => parse_spaced(pattern, &Syntax::of(dialect))?,
+    };
+
+    if pattern.is_empty() {
+        return Err(PatternError {
+            pattern: 0,
+            token: 0,
+            column: 0,
+            kind: PatternErrorKind::EmptyPattern,
+        });
+    }
+    Ok(pattern)
+}
+
+/// Guesses the dialect of `pattern` from its shape.
+///
+/// Code-style patterns start with `\x`, compact patterns are a single token
+/// of hex digits and `?` longer than one byte (other than a `0b` bit pattern)
+/// with at least one hex digit, everything else is treated as
+/// space-separated tokens.
+pub(crate) fn detect_dialect(pattern: &str) -> Dialect {
+    let trimmed = pattern.trim_start();
+    if trimmed.starts_with("\\x") || trimmed.starts_with("\\X") {
+        return Dialect::Code;
+    }
+
+    let mut parts = tokens(pattern);
+    if let (Some((_, token)), None) = (parts.next(), parts.next())
+        && token.len() > 2
+        && token.bytes().all(|b| b.is_ascii_hexdigit() || b == b'?')
+        && token.bytes().any(|b| b.is_ascii_hexdigit())
+        && !is_bits(token)
+    {
+        return Dialect::Compact;
+    }
+
+    Dialect::Native
+}
+
+fn parse_spaced(pattern: &str, syntax: &Syntax) -> Result<Pattern, PatternError> {
+    let mut out = Pattern::new();
+    let mut last = (0, 0);
+
+    let mut parts = tokens(pattern).enumerate();
+    let mut resolving = None;
+
+    for (index, (column, part)) in parts.by_ref() {
+        if syntax.extended && part == "=>" {
+            resolving = Some(index);
+            break;
+        }
+        parse_token(part, syntax, &mut out).map_err(|(offset, kind)| PatternError {
+            pattern: 0,
+            token: index,
+            column: column + offset,
+            kind,
+        })?;
+        last = (index, column);
+    }
+
+    // `=> step => step ...`: the first separator has been consumed, so
+    // steps and separators alternate from here on
+    if let Some(separator) = resolving {
+        let invalid = |token, column| PatternError {
+            pattern: 0,
+            token,
+            column,
+            kind: PatternErrorKind::InvalidResolveStep,
+        };
+        let mut dangling = Some((separator + 1, pattern.len()));
+
+        for (i, (index, (column, part))) in parts.enumerate() {
+            if i % 2 == 1 {
+                if part != "=>" {
+                    return Err(invalid(index, column));
+                }
+                dangling = Some((index + 1, pattern.len()));
+            } else {
+                let step = Relative::parse(part).ok_or_else(|| invalid(index, column))?;
+                out.resolve.push(step);
+                dangling = None;
+            }
+        }
+
+        if let Some((index, column)) = dangling {
+            return Err(invalid(index, column));
+        }
+    }
+
+    if out.gaps.last().is_some_and(|gap| gap.at == out.len()) {
+        return Err(PatternError {
+            pattern: 0,
+            token: last.0,
+            column: last.1,
+            kind: PatternErrorKind::MisplacedJump,
+        });
+    }
+
+    Ok(out)
+}
+
+/// Parses a single whitespace-separated token and appends it to `out`.
+fn parse_token(token: &str, syntax: &Syntax, out: &mut Pattern) -> Result<(), TokenError> {
+    if syntax.extended
+        && let Some(rest) = token.strip_prefix('^')
+    {
+        if out.target.replace(out.len()).is_some() {
+            return Err((0, PatternErrorKind::DuplicateMarker));
+        }
+        if rest.is_empty() {
+            return Ok(());
+        }
+        return parse_token(rest, syntax, out).map_err(|(offset, kind)| (offset + 1, kind));
+    }
+
+    if syntax.extended
+        && token.len() > 1
+        && let Some(rest) = token.strip_prefix('!')
+    {
+        let mut inner = Pattern::new();
+        parse_token(rest, syntax, &mut inner).map_err(|(offset, kind)| (offset + 1, kind))?;
+        if inner.len() != 1 || !inner.gaps.is_empty() || !inner.captures.is_empty() {
+            return Err((1, PatternErrorKind::InvalidNegation));
+        }
+
+        let class = inner.position_class(0).negate();
+        if class.is_empty() {
+            return Err((0, PatternErrorKind::InvalidNegation));
+        }
+        out.push_class(class);
+        return Ok(());
+    }
+
+    match token.as_bytes()[0] {
+        b'[' if syntax.extended && is_jump(token) => {
+            let (min, max) = parse_jump(token)?;
+            if min != max && out.is_empty() {
+                return Err((0, PatternErrorKind::MisplacedJump));
+            }
+            out.push_gap(min, max).map_err(|kind| (1, kind))?;
+        }
+        b'[' if syntax.extended => out.push_class(parse_class(token)?),
+        b'(' if syntax.extended => out.push_class(parse_alternation(token)?),
+        _ if syntax.extended && is_string(token) => parse_string(token, out)?,
+        b'<' if syntax.extended => {
+            let (name, ty) = parse_capture(token)?;
+            if out.captures.iter().any(|c| c.name == name) {
+                return Err((1, PatternErrorKind::DuplicateCapture));
+            }
+            out.push_capture(name, ty);
+        }
+        _ if syntax.extended && token.len() > 2 && token.starts_with("0b") => {
+            let (value, mask) = parse_bits(token)?;
+            out.push(value, mask);
+        }
+        _ if syntax.extended && token.contains(['&', ':']) => {
+            let (value, mask) = parse_masked(token)?;
+            out.push(value, mask);
+        }
+        _ => {
+            let (value, mask) = parse_byte(token, syntax)?;
+            out.push(value, mask);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the contents of a group token, which must end with `close`.
+fn group_body(token: &str, close: u8) -> Result<&str, TokenError> {
+    if token.len() < 2 || token.as_bytes()[token.len() - 1] != close {
+        return Err((token.len(), PatternErrorKind::UnclosedGroup));
+    }
+    Ok(&token[1..token.len() - 1])
+}
+
+/// Whether a token is a string literal, possibly with a prefix such as `u`.
+fn is_string(token: &str) -> bool {
+    token
+        .find('"')
+        .is_some_and(|quote| token[..quote].bytes().all(|b| b.is_ascii_alphabetic()))
+}
+
+/// A decoded unit of a string literal.
+enum StringUnit {
+    Char(char),
+    /// A `\xNN` escape, emitted as a raw byte (or UTF-16 code unit).
+    Raw(u8),
+}
+
+/// Parses `"text"`, `u"text"` (UTF-16LE) or `i"text"` (ASCII
+/// case-insensitive), appending its bytes to `out`. The prefixes combine,
+/// as in `iu"text"`.
+fn parse_string(token: &str, out: &mut Pattern) -> Result<(), TokenError> {
+    let quote = token.find('"').unwrap_or_default();
+    let prefix = &token[..quote];
+    let wide = prefix.contains('u');
+    let nocase = prefix.contains('i');
+    if prefix.len() > usize::from(wide) + usize::from(nocase) {
+        return Err((0, PatternErrorKind::InvalidStringPrefix));
+    }
+
+    let mut units = Vec::new();
+    let mut chars = token.char_indices().skip(quote + 1);
+    let close = loop {
+        let Some((offset, c)) = chars.next() else {
+            return Err((token.len(), PatternErrorKind::UnterminatedString));
+        };
+        let unit = match c {
+            '"' => break offset,
+            '\\' => match chars.next() {
+                Some((_, 'n')) => StringUnit::Char('\n'),
+                Some((_, 'r')) => StringUnit::Char('\r'),
+                Some((_, 't')) => StringUnit::Char('\t'),
+                Some((_, '0')) => StringUnit::Char('\0'),
+                Some((_, c @ ('\\' | '"'))) => StringUnit::Char(c),
+                Some((_, 'x')) => {
+                    let digits = token
+                        .get(offset + 2..offset + 4)
+                        .filter(|d| d.bytes().all(|b| b.is_ascii_hexdigit()))
+                        .ok_or((offset, PatternErrorKind::InvalidEscape))?;
+                    chars.nth(1);
+                    StringUnit::Raw(u8::from_str_radix(digits, 16).unwrap_or_default())
+                }
+                _ => return Err((offset, PatternErrorKind::InvalidEscape)),
+            },
+            c => StringUnit::Char(c),
+        };
+        units.push(unit);
+    };
+
+    if close + 1 != token.len() {
+        return Err((close + 1, PatternErrorKind::TokenTooLong));
+    }
+    if units.is_empty() {
+        return Err((quote, PatternErrorKind::EmptyGroup));
+    }
+
+    let mut push = |b: u8, letter: bool| {
+        if nocase && letter {
+            let mut class = ByteClass::new();
+            class.insert(b.to_ascii_lowercase());
+            class.insert(b.to_ascii_uppercase());
+            out.push_class(class);
+        } else {
+            out.push(b, 0xFF);
+        }
+    };
+    for unit in units {
+        match unit {
+            StringUnit::Char(c) if wide => {
+                for u in c.encode_utf16(&mut [0; 2]) {
+                    let [lo, hi] = u.to_le_bytes();
+                    push(lo, c.is_ascii_alphabetic());
+                    push(hi, false);
+                }
+            }
+            StringUnit::Char(c) => {
+                for &b in c.encode_utf8(&mut [0; 4]).as_bytes() {
+                    push(b, c.is_ascii_alphabetic());
+                }
+            }
+            StringUnit::Raw(b) if wide => {
+                push(b, false);
+                push(0, false);
+            }
+            StringUnit::Raw(b) => push(b, false),
+        }
+    }
+    Ok(())
+}
+
+/// Parses `<name:type>` into the capture name and its type.
+fn parse_capture(token: &str) -> Result<(&str, CaptureType), TokenError> {
+    let body = group_body(token, b'>')?;
+    let Some((name, ty)) = body.split_once(':') else {
+        return Err((1, PatternErrorKind::InvalidCapture));
+    };
+
+    let identifier = name
+        .bytes()
+        .next()
+        .is_some_and(|b| b.is_ascii_alphabetic() || b == b'_')
+        && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_');
+    if !identifier {
+        return Err((1, PatternErrorKind::InvalidCapture));
+    }
+
+    let ty = CaptureType::parse(ty).ok_or((name.len() + 2, PatternErrorKind::InvalidCapture))?;
+    Ok((name, ty))
+}
+
+/// Parses `8B&C7` or `8B:C7`: a byte compared only on the bits set in the mask.
+fn parse_masked(token: &str) -> Result<(u8, u8), TokenError> {
+    let split = token.find(['&', ':']).unwrap_or(token.len());
+    let exact = |s: &str, at: usize| match parse_byte(s, &Syntax::of(Dialect::Native)) {
+        Ok((value, 0xFF)) => Ok(value),
+        Ok(_) => Err((at, PatternErrorKind::InvalidWildcard)),
+        Err((o, kind)) => Err((at + o, kind)),
+    };
+
+    let value = exact(&token[..split], 0)?;
+    let mask = exact(&token[split + 1..], split + 1)?;
+    Ok((value & mask, mask))
+}
+
+fn is_bits(token: &str) -> bool {
+    token
+        .strip_prefix("0b")
+        .is_some_and(|bits| !bits.is_empty() && bits.bytes().all(|b| b"01?_".contains(&b)))
+}
+
+/// Parses `0b01??_?101`: eight bits, most significant first, where `?`
+/// ignores a bit and `_` is a separator.
+fn parse_bits(token: &str) -> Result<(u8, u8), TokenError> {
+    let mut value = 0u8;
+    let mut mask = 0u8;
+    let mut bits = 0;
+
+    for (offset, c) in token.char_indices().skip(2) {
+        let (bit, known) = match c {
+            '_' => continue,
+            '0' => (0, 1),
+            '1' => (1, 1),
+            '?' => (0, 0),
+            _ => return Err((offset, PatternErrorKind::InvalidBinaryDigit(c))),
+        };
+        if bits == 8 {
+            return Err((offset, PatternErrorKind::TokenTooLong));
+        }
+        value = value << 1 | bit;
+        mask = mask << 1 | known;
+        bits += 1;
+    }
+
+    if bits != 8 {
+        return Err((token.len(), PatternErrorKind::IncompleteByte));
+    }
+    Ok((value, mask))
+}
+
+/// Whether a bracketed token is a YARA-style jump: `[n]`, `[n-m]`, `[n-]`
+/// or `[-]` with decimal bounds.
+fn is_jump(token: &str) -> bool {
+    let body = token
+        .strip_prefix('[')
+        .and_then(|t| t.strip_suffix(']'))
+        .unwrap_or_default()
+        .trim();
+    let decimal = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+
+    match body.split_once('-') {
+        Some((lo, hi)) => decimal(lo.trim_end()) && decimal(hi.trim_start()),
+        None => !body.is_empty() && decimal(body),
+    }
+}
+
+/// Parses a jump accepted by [`is_jump`] into its inclusive bounds, neither
+/// of which may exceed [`MAX_JUMP`].
+fn parse_jump(token: &str) -> Result<(usize, usize), TokenError> {
+    let body = &token[1..token.len() - 1];
+    let bound = |s: &str| s.trim().parse::<usize>().ok();
+
+    let (min, max) = match body.split_once('-') {
+        Some((lo, hi)) => (bound(lo), bound(hi)),
+        None => (bound(body), bound(body)),
+    };
+    match (min, max) {
+        (Some(min), Some(max)) if min <= max && max <= MAX_JUMP => Ok((min, max)),
+        _ => Err((1, PatternErrorKind::InvalidJump)),
+    }
+}
+
+/// Parses `[40-4F]` or `[48 4C 49]`: a set of bytes, nibble wildcards and
+/// inclusive ranges.
+fn parse_class(token: &str) -> Result<ByteClass, TokenError> {
+    let body = group_body(token, b']')?;
+    let syntax = Syntax::of(Dialect::Native);
+    let mut class = ByteClass::new();
+
+    for (column, item) in tokens(body) {
+        let offset = |(o, kind): TokenError| (1 + column + o, kind);
+
+        if let Some(dash) = item.find('-') {
+            let bound = |s: &str, at: usize| match parse_byte(s, &syntax) {
+                Ok((value, 0xFF)) if s.len() == 2 => Ok(value),
+                Ok(_) => Err((at, PatternErrorKind::InvalidRange)),
+                Err((o, kind)) => Err((at + o, kind)),
+            };
+            let lo = bound(&item[..dash], 0).map_err(offset)?;
+            let hi = bound(&item[dash + 1..], dash + 1).map_err(offset)?;
+            if lo > hi {
+                return Err(offset((0, PatternErrorKind::InvalidRange)));
+            }
+            class.insert_range(lo, hi);
+        } else {
+            let (value, mask) = parse_byte(item, &syntax).map_err(offset)?;
+            class.union(&ByteClass::masked(value, mask));
+        }
+    }
+
+    if class.is_empty() {
+        return Err((0, PatternErrorKind::EmptyGroup));
+    }
+    Ok(class)
+}
+
+/// Parses `(48|4C)`: a choice between single-byte alternatives.
+fn parse_alternation(token: &str) -> Result<ByteClass, TokenError> {
+    let body = group_body(token, b')')?;
+    let syntax = Syntax::of(Dialect::Native);
+    let mut class = ByteClass::new();
+
+    let mut column = 1;
+    for alternative in body.split('|') {
+        let mut items = tokens(alternative);
+        let Some((offset, item)) = items.next() else {
+            return Err((column, PatternErrorKind::EmptyGroup));
+        };
+        if let Some((offset, _)) = items.next() {
+            return Err((column + offset, PatternErrorKind::UnsupportedAlternation));
+        }
+
+        let (value, mask) =
+            parse_byte(item, &syntax).map_err(|(o, kind)| (column + offset + o, kind))?;
+        class.union(&ByteClass::masked(value, mask));
+        column += alternative.len() + 1;
+    }
+
+    Ok(class)
+}
+
+/// Parses `"488B??0?"`: pairs of hex digits or `?` with no separators.
+fn parse_compact(pattern: &str) -> Result<Pattern, PatternError> {
+    let syntax = Syntax::of(Dialect::Compact);
+    let mut out = Pattern::new();
+
+    let column = pattern.len() - pattern.trim_start().len();
+    let body = pattern.trim();
+
+    for (index, start) in (0..body.len()).step_by(2).enumerate() {
+        let error = |offset: usize, kind| PatternError {
+            pattern: 0,
+            token: index,
+            column: column + start + offset,
+            kind,
+        };
+
+        if start + 2 > body.len() {
+            return Err(error(0, PatternErrorKind::IncompleteByte));
+        }
+        let pair = body
+            .get(start..start + 2)
+            .ok_or_else(|| error(0, invalid_char(&body[start..], 0)))?;
+
+        let (value, mask) =
+            parse_byte(pair, &syntax).map_err(|(offset, kind)| error(offset, kind))?;
+        out.push(value, mask);
+    }
+
+    Ok(out)
+}
+
+/// Parses `"\x48\x8B\x00\x00\x05 xx??x"`: escaped bytes followed by an
+/// optional mask where `x` keeps a byte and `?` ignores it.
+fn parse_code(pattern: &str) -> Result<Pattern, PatternError> {
+    let mut parts = tokens(pattern);
+    let (column, bytes) = parts.next().unwrap_or((0, ""));
+    let mask = parts.next();
+
+    let mut values = Vec::with_capacity(bytes.len() / 4);
+    let mut index = 0;
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let error = |offset: usize, kind| PatternError {
+            pattern: 0,
+            token: index,
+            column: column + offset,
+            kind,
+        };
+
+        let rest = &bytes.as_bytes()[offset..];
+        if rest[0] != b'\\' {
+            return Err(error(offset, invalid_char(bytes, offset)));
+        }
+        if rest.len() < 2 || !matches!(rest[1], b'x' | b'X') {
+            return Err(error(offset + 1, PatternErrorKind::InvalidEscape));
+        }
+
+        let digits = rest[2..]
+            .iter()
+            .take(2)
+            .take_while(|b| b.is_ascii_hexdigit())
+            .count();
+        if digits == 0 {
+            return Err(error(offset + 2, PatternErrorKind::IncompleteByte));
+        }
+        let value = rest[2..2 + digits]
+            .iter()
+            .fold(0, |acc, &c| acc << 4 | hex_digit(c).unwrap_or(0));
+
+        values.push(value);
+        index += 1;
+        offset += 2 + digits;
+    }
+
+    let masks = match mask {
+        None => vec![0xFF; values.len()],
+        Some((mask_column, mask)) => {
+            let masks = mask
+                .bytes()
+                .enumerate()
+                .map(|(i, c)| match c {
+                    b'x' | b'X' => Ok(0xFF),
+                    b'?' | b'.' => Ok(0x00),
+                    _ => Err(PatternError {
+                        pattern: 0,
+                        token: i,
+                        column: mask_column + i,
+                        kind: invalid_char(mask, i),
+                    }),
+                })
+                .collect::<Result<Vec<u8>, _>>()?;
+
+            if masks.len() != values.len() {
+                return Err(PatternError {
+                    pattern: 0,
+                    token: masks.len().min(values.len()),
+                    column: mask_column,
+                    kind: PatternErrorKind::MaskLengthMismatch,
+                });
+            }
+            masks
+        }
+    };
+
+    if let Some((column, _)) = parts.next() {
+        return Err(PatternError {
+            pattern: 0,
+            token: values.len(),
+            column,
+            kind: PatternErrorKind::TokenTooLong,
+        });
+    }
+
+    let mut out = Pattern::new();
+    for (value, mask) in values.into_iter().zip(masks) {
+        out.push(value, mask);
+    }
+    Ok(out)
+}
+
+/// Parses a single byte token (`48`, `??`, `4?`, `?8`, `?`) into a value/mask pair.
+fn parse_byte(token: &str, syntax: &Syntax) -> Result<(u8, u8), TokenError> {
+    let digit = |offset: usize| {
+        hex_digit(token.as_bytes()[offset]).ok_or((offset, invalid_char(token, offset)))
+    };
+    let nibble = |offset: usize| {
+        if syntax.nibbles {
+            Ok(())
+        } else {
+            Err((offset, PatternErrorKind::InvalidWildcard))
+        }
+    };
+
+    match token.as_bytes() {
+        [] => Err((0, PatternErrorKind::IncompleteByte)),
+        &[a, b] if syntax.is_wildcard(a) && syntax.is_wildcard(b) => Ok((0x00, 0x00)),
+        &[_, b] if syntax.is_wildcard(b) => {
+            nibble(1)?;
+            Ok((digit(0)? << 4, 0xF0))
+        }
+        &[a, _] if syntax.is_wildcard(a) => {
+            nibble(0)?;
+            Ok((digit(1)?, 0x0F))
+        }
+        [_, _] => Ok((digit(0)? << 4 | digit(1)?, 0xFF)),
+        &[a] if syntax.is_wildcard(a) => {
+            if syntax.single_wildcard {
+                Ok((0x00, 0x00))
+            } else {
+                Err((0, PatternErrorKind::InvalidWildcard))
+            }
+        }
+        [_] => Ok((digit(0)?, 0xFF)),
+        long if long.iter().all(|&b| syntax.is_wildcard(b)) => {
+            Err((0, PatternErrorKind::InvalidWildcard))
+        }
+        _ => Err((0, PatternErrorKind::TokenTooLong)),
+    }
+}
+
+fn invalid_char(s: &str, offset: usize) -> PatternErrorKind {
+    let c = s
+        .get(offset..)
+        .and_then(|rest| rest.chars().next())
+        .unwrap_or(char::REPLACEMENT_CHARACTER);
+    PatternErrorKind::InvalidHexDigit(c)
+}
+
+pub(crate) fn hex_digit(c: u8) -> Option<u8> {
+    (c as char).to_digit(16).map(|d| d as u8)
+}
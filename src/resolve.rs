@@ -0,0 +1,113 @@
+use std::fmt::Display;
+
+/// Computes the target of a relative displacement: the signed `size`-byte
+/// little-endian value at `disp_pos` added to `insn_end`, the offset of the
+/// first byte after the instruction.
+///
+/// Returns `None` if the displacement does not fit in `data`, `size` is not
+/// 1, 2, 4 or 8, or the target would be negative.
+///
+/// # Example
+///
+/// ```rust
+/// # use hexpotter::resolve::relative_target;
+/// // call +0x10
+/// let data = [0xE8, 0x10, 0x00, 0x00, 0x00];
+/// assert_eq!(relative_target(&data, 1, 4, 5), Some(0x15));
+/// ```
+pub fn relative_target(
+    data: &[u8],
+    disp_pos: usize,
+    size: usize,
+    insn_end: usize,
+) -> Option<usize> {
+    let bytes = data.get(disp_pos..disp_pos.checked_add(size)?)?;
+    let disp = match size {
+        1 => bytes[0] as i8 as i64,
+        2 => i16::from_le_bytes(bytes.try_into().ok()?) as i64,
+        4 => i32::from_le_bytes(bytes.try_into().ok()?) as i64,
+        8 => i64::from_le_bytes(bytes.try_into().ok()?),
+        _ => return None,
+    };
+    insn_end.checked_add_signed(disp.try_into().ok()?)
+}
+
+/// A relative operand of an instruction: the displacement is `size` bytes
+/// at `disp` bytes into the instruction, and is relative to the end of the
+/// instruction, `len` bytes after its start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Relative {
+    pub(crate) disp: usize,
+    pub(crate) size: usize,
+    pub(crate) len: usize,
+}
+
+impl Relative {
+    /// `call rel32` (`E8`), also valid for `jmp rel32` (`E9`).
+    pub const CALL: Relative = Relative::new(1, 4, 5);
+    /// `jmp rel32` (`E9`).
+    pub const JMP: Relative = Relative::new(1, 4, 5);
+    /// `jmp rel8` (`EB`) and the short conditional jumps (`7x`).
+    pub const JMP8: Relative = Relative::new(1, 1, 2);
+    /// Near conditional jumps `jcc rel32` (`0F 8x`).
+    pub const JCC: Relative = Relative::new(2, 4, 6);
+    /// A bare 32-bit displacement that ends the instruction, as in
+    /// `lea rax, [rip+disp32]` when the match target points at the displacement.
+    pub const RIP: Relative = Relative::new(0, 4, 4);
+
+    pub const fn new(disp: usize, size: usize, len: usize) -> Self {
+        Relative { disp, size, len }
+    }
+
+    /// Resolves the operand of the instruction starting at `insn`.
+    pub fn resolve(&self, data: &[u8], insn: usize) -> Option<usize> {
+        relative_target(
+            data,
+            insn.checked_add(self.disp)?,
+            self.size,
+            insn.checked_add(self.len)?,
+        )
+    }
+
+    /// Parses a resolution step: `call`, `jmp`, `jmp8`, `jcc`, `rip` or
+    /// `rel(disp, size, len)`.
+    pub(crate) fn parse(step: &str) -> Option<Self> {
+        match step {
+            "call" => Some(Relative::CALL),
+            "jmp" => Some(Relative::JMP),
+            "jmp8" => Some(Relative::JMP8),
+            "jcc" => Some(Relative::JCC),
+            "rip" => Some(Relative::RIP),
+            _ => {
+                let args = step.strip_prefix("rel(")?.strip_suffix(')')?;
+                let mut args = args.split(',').map(|arg| arg.trim().parse::<usize>());
+                let (Some(Ok(disp)), Some(Ok(size)), Some(Ok(len)), None) =
+                    (args.next(), args.next(), args.next(), args.next())
+                else {
+                    return None;
+                };
+                matches!(size, 1 | 2 | 4 | 8).then_some(Relative::new(disp, size, len))
+            }
+        }
+    }
+}
+
+impl Display for Relative {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Relative::CALL => write!(f, "call"),
+            Relative::JMP8 => write!(f, "jmp8"),
+            Relative::JCC => write!(f, "jcc"),
+            Relative::RIP => write!(f, "rip"),
+            Relative { disp, size, len } => write!(f, "rel({disp}, {size}, {len})"),
+        }
+    }
+}
+
+/// Follows `steps` in order, each one resolving the instruction found at
+/// the previous target, starting from `insn`.
+pub(crate) fn follow(data: &[u8], insn: usize, steps: &[Relative]) -> Option<usize> {
+    steps
+        .iter()
+        .try_fold(insn, |insn, step| step.resolve(data, insn))
+}
@@ -0,0 +1,293 @@
+//! Line-based signature files.
+//!
+//! Each non-blank line is either a signature, `name = pattern`, or a metadata
+//! entry, `@key = value`, attached to the signature above it. Everything after
+//! a `#` outside a string literal is a comment. The `tags` key holds a comma
+//! separated list:
+//!
+//! ```text
+//! # game.exe, build 1.4
+//! get_player = 48 8B 05 ^?? ?? ?? ?? 48 85 C0   # singleton accessor
+//!   @tags = player, entity
+//!   @module = game.exe
+//! draw_call = E8 ^?? ?? ?? ?? 84 C0 74 ?? => call
+//! ```
+//!
+//! Signatures are numbered in file order, so the [`PatternId`]s reported by
+//! [`Signatures::scanner`] index straight back into the file.
+
+use std::{fmt::Display, path::Path};
+
+use crate::{
+    Hexpotter, Pattern, PatternError, PatternId,
+    source::{self, LoadError, SourceError},
+};
+
+/// A named pattern loaded from a signature file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    name: String,
+    pattern: Pattern,
+    tags: Vec<String>,
+    metadata: Vec<(String, String)>,
+    line: usize,
+}
+
+impl Signature {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Value of the `@key` entry, if present.
+    pub fn metadata(&self, key: &str) -> Option<&str> {
+        self.metadata
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Every `@key = value` entry other than `tags`, in file order.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.metadata.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// 1-based line of the `name = pattern` entry, which its metadata
+    /// follows.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+/// A parsed signature file, mapping [`PatternId`]s to names and metadata.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Signatures {
+    signatures: Vec<Signature>,
+}
+
+impl Signatures {
+    /// Parses a signature file, returning every error found rather than
+    /// stopping at the first one.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexpotter::{Scan, Signatures};
+    /// let signatures = Signatures::parse(
+    ///     "get_player = 48 8B 05 ^?? ?? ?? ??  # singleton\n\
+    ///      @tags = player, entity\n",
+    /// )
+    /// .unwrap();
+    /// let scanner = signatures.scanner();
+    ///
+    /// let data = [0x90, 0x48, 0x8B, 0x05, 0x10, 0x00, 0x00, 0x00];
+    /// scanner.scan(&data, |m| {
+    ///     let signature = signatures.get(m.id()).unwrap();
+    ///     assert_eq!(signature.name(), "get_player");
+    ///     assert!(signature.has_tag("entity"));
+    ///     Scan::Continue
+    /// });
+    /// ```
+    pub fn parse(source: &str) -> Result<Self, Vec<SignatureError>> {
+        let mut signatures: Vec<Signature> = Vec::new();
+        let mut errors = Vec::new();
+        // metadata following a broken signature is dropped with it
+        let mut orphaned = false;
+
+        for (index, line) in source.lines().enumerate() {
+            let number = index + 1;
+            let line = strip_comment(line);
+            let indent = line.len() - line.trim_start().len();
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let error = |kind| SignatureError { line: number, kind };
+
+            let Some((key, value)) = line.split_once('=') else {
+                errors.push(error(SignatureErrorKind::MissingSeparator));
+                continue;
+            };
+            // where the pattern starts in the line, for its error columns
+            let column = indent + key.len() + 1 + value.len() - value.trim_start().len();
+            let (key, value) = (key.trim(), value.trim());
+
+            if let Some(key) = key.strip_prefix('@') {
+                if !is_name(key) {
+                    errors.push(error(SignatureErrorKind::InvalidName));
+                    continue;
+                }
+                if orphaned {
+                    continue;
+                }
+                let Some(signature) = signatures.last_mut() else {
+                    errors.push(error(SignatureErrorKind::OrphanMetadata));
+                    continue;
+                };
+
+                if key == "tags" {
+                    signature.tags.extend(
+                        value
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|t| !t.is_empty())
+                            .map(str::to_owned),
+                    );
+                } else if signature.metadata(key).is_some() {
+                    errors.push(error(SignatureErrorKind::DuplicateKey(key.to_owned())));
+                } else {
+                    signature.metadata.push((key.to_owned(), value.to_owned()));
+                }
+                continue;
+            }
+
+            orphaned = true;
+            if !is_name(key) {
+                errors.push(error(SignatureErrorKind::InvalidName));
+                continue;
+            }
+            if signatures.iter().any(|s| s.name == key) {
+                errors.push(error(SignatureErrorKind::DuplicateName(key.to_owned())));
+                continue;
+            }
+
+            match Pattern::parse(value) {
+                Ok(pattern) => {
+                    orphaned = false;
+                    signatures.push(Signature {
+                        name: key.to_owned(),
+                        pattern,
+                        tags: Vec::new(),
+                        metadata: Vec::new(),
+                        line: number,
+                    });
+                }
+                Err(e) => {
+                    let column = column + e.column;
+                    let e = e.with_pattern(signatures.len()).with_column(column);
+                    errors.push(error(SignatureErrorKind::Pattern(e)));
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(Signatures { signatures })
+    }
+
+    /// Reads and parses the signature file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LoadError<SignatureError>> {
+        source::load(path, Self::parse)
+    }
+
+    /// A scanner over every signature. Signatures keep their file order, so a
+    /// reported [`PatternId`] is the signature's index for [`Signatures::get`].
+    pub fn scanner(&self) -> Hexpotter {
+        Hexpotter::from_patterns(self.signatures.iter().map(|s| s.pattern.clone()))
+    }
+
+    pub fn get(&self, id: PatternId) -> Option<&Signature> {
+        self.signatures.get(id.usize())
+    }
+
+    /// Looks up a signature by name, returning its [`PatternId`].
+    pub fn id(&self, name: &str) -> Option<PatternId> {
+        self.signatures
+            .iter()
+            .position(|s| s.name == name)
+            .map(PatternId)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (PatternId, &Signature)> {
+        self.signatures
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (PatternId(i), s))
+    }
+
+    pub fn len(&self) -> usize {
+        self.signatures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.signatures.is_empty()
+    }
+}
+
+/// Cuts `line` at the first `#` that is not inside a string literal.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn is_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':' | '/'))
+}
+
+#[derive(Debug)]
+pub enum SignatureErrorKind {
+    /// The line has no `=` between the name and the pattern.
+    MissingSeparator,
+    InvalidName,
+    DuplicateName(String),
+    DuplicateKey(String),
+    /// A `@key = value` line appears before any signature.
+    OrphanMetadata,
+    /// The pattern does not compile. Its column counts from the start of the
+    /// line rather than of the pattern.
+    Pattern(PatternError),
+}
+
+/// An error in a signature file, on the line of the offending entry.
+pub type SignatureError = SourceError<SignatureErrorKind>;
+
+impl Display for SignatureErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignatureErrorKind::MissingSeparator => write!(f, "expected `name = pattern`"),
+            SignatureErrorKind::InvalidName => write!(f, "invalid name"),
+            SignatureErrorKind::DuplicateName(name) => write!(f, "duplicate signature {name:?}"),
+            SignatureErrorKind::DuplicateKey(key) => write!(f, "duplicate metadata key {key:?}"),
+            SignatureErrorKind::OrphanMetadata => write!(f, "metadata before any signature"),
+            SignatureErrorKind::Pattern(e) => {
+                write!(f, "token {} (column {}): {}", e.token, e.column, e.kind)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SignatureErrorKind {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SignatureErrorKind::Pattern(e) => Some(e),
+            _ => None,
+        }
+    }
+}
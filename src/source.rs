@@ -0,0 +1,79 @@
+//! Errors shared by the file formats patterns are loaded from:
+//! [`Signatures`](crate::Signatures), [`YaraRules`](crate::YaraRules) and
+//! [`FlirtSignatures`](crate::FlirtSignatures).
+
+use std::{error::Error, fmt::Display, fs, io, path::Path};
+
+/// An error found while parsing a pattern file, with the 1-based line it was
+/// found on. `K` says what went wrong in terms of the file's format.
+#[derive(Debug)]
+pub struct SourceError<K> {
+    pub(crate) line: usize,
+    pub(crate) kind: K,
+}
+
+impl<K> SourceError<K> {
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn kind(&self) -> &K {
+        &self.kind
+    }
+}
+
+impl<K: Display> Display for SourceError<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+impl<K: Error + 'static> Error for SourceError<K> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.kind.source()
+    }
+}
+
+/// Why a pattern file could not be loaded from disk.
+#[derive(Debug)]
+pub enum LoadError<E> {
+    /// The file could not be read.
+    Io(io::Error),
+    /// The file was read but holds errors, all of which are reported.
+    Parse(Vec<E>),
+}
+
+impl<E: Display> Display for LoadError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "{e}"),
+            LoadError::Parse(errors) => {
+                for (i, error) in errors.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{error}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for LoadError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadError::Io(e) => Some(e),
+            LoadError::Parse(errors) => errors.first().map(|e| e as &(dyn Error + 'static)),
+        }
+    }
+}
+
+/// Reads the file at `path` and hands its contents to `parse`.
+pub(crate) fn load<T, E>(
+    path: impl AsRef<Path>,
+    parse: impl FnOnce(&str) -> Result<T, Vec<E>>,
+) -> Result<T, LoadError<E>> {
+    let source = fs::read_to_string(path).map_err(LoadError::Io)?;
+    parse(&source).map_err(LoadError::Parse)
+}
@@ -0,0 +1,773 @@
+//! Importer for the hex strings of YARA rules.
+//!
+//! Only the `strings:` section is read; `meta:` and `condition:` are skipped.
+//! Hex strings are translated to the native syntax, which is close to a
+//! superset of YARA's: wildcards, nibbles, `~XX` negation, bounded jumps and
+//! single-byte alternations map directly. Alternations of longer sequences
+//! are expanded into one pattern per combination, so a string may own
+//! several [`PatternId`]s.
+//!
+//! Text strings, regular expressions and unbounded jumps have no equivalent
+//! and are skipped; each one is reported by [`YaraRules::skipped`].
+
+use std::{fmt::Display, path::Path};
+
+use crate::{
+    Dialect, Hexpotter, Pattern, PatternError, PatternId,
+    source::{self, LoadError, SourceError},
+};
+
+/// Maximum number of patterns a single hex string may expand into.
+const MAX_ALTERNATIVES: usize = 64;
+
+/// A hex string imported from a YARA rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct YaraString {
+    rule: String,
+    identifier: String,
+    patterns: Vec<PatternId>,
+    line: usize,
+}
+
+impl YaraString {
+    pub fn rule(&self) -> &str {
+        &self.rule
+    }
+
+    /// The string identifier, including its `$`.
+    pub fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    /// Patterns compiled from this string; more than one when it contains
+    /// multi-byte alternations.
+    pub fn patterns(&self) -> &[PatternId] {
+        &self.patterns
+    }
+
+    /// 1-based line holding the string's identifier. The hex string itself
+    /// may go on over the following lines.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+/// The hex strings of a set of YARA rules, compiled to patterns.
+#[derive(Debug, Default)]
+pub struct YaraRules {
+    strings: Vec<YaraString>,
+    patterns: Vec<Pattern>,
+    owners: Vec<usize>,
+    skipped: Vec<YaraError>,
+}
+
+impl YaraRules {
+    /// Imports the hex strings of every rule in `source`.
+    ///
+    /// Malformed rules and hex strings fail the whole import; strings using
+    /// constructs without a pattern equivalent are skipped instead and listed
+    /// by [`YaraRules::skipped`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use hexpotter::{Scan, YaraRules};
+    /// let rules = YaraRules::parse(r#"
+    ///     rule pe_header {
+    ///         strings:
+    ///             $mz = { 4D 5A ?? [2-4] 50 45 }
+    ///             $name = "kernel32.dll"
+    ///         condition:
+    ///             $mz
+    ///     }
+    /// "#)
+    /// .unwrap();
+    /// assert_eq!(rules.skipped().len(), 1);
+    ///
+    /// let data = [0x4D, 0x5A, 0x90, 0x00, 0x00, 0x50, 0x45];
+    /// let scanner = rules.scanner();
+    /// scanner.scan(&data, |m| {
+    ///     assert_eq!(rules.get(m.id()).unwrap().identifier(), "$mz");
+    ///     Scan::Continue
+    /// });
+    /// ```
+    pub fn parse(source: &str) -> Result<Self, Vec<YaraError>> {
+        let mut rules = YaraRules::default();
+        let mut errors = Vec::new();
+        let mut cursor = Cursor::new(source);
+
+        while let Err(error) = rules.parse_file(&mut cursor, &mut errors) {
+            errors.push(error);
+            // resynchronize on the next rule
+            if !cursor.skip_to_rule() {
+                break;
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(rules)
+    }
+
+    /// Reads and imports the YARA rule file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LoadError<YaraError>> {
+        source::load(path, Self::parse)
+    }
+
+    /// A scanner over the imported patterns. A string expanded into several
+    /// patterns owns several [`PatternId`]s, each mapped back to it by
+    /// [`YaraRules::get`].
+    pub fn scanner(&self) -> Hexpotter {
+        Hexpotter::from_patterns(self.patterns.iter().cloned())
+    }
+
+    /// The string a reported [`PatternId`] was compiled from.
+    pub fn get(&self, id: PatternId) -> Option<&YaraString> {
+        self.owners.get(id.usize()).map(|&i| &self.strings[i])
+    }
+
+    /// Looks up a string by rule name and identifier (e.g. `"$mz"`).
+    pub fn find(&self, rule: &str, identifier: &str) -> Option<&YaraString> {
+        self.strings
+            .iter()
+            .find(|s| s.rule == rule && s.identifier == identifier)
+    }
+
+    pub fn strings(&self) -> &[YaraString] {
+        &self.strings
+    }
+
+    /// Strings that were not imported because they use unsupported constructs.
+    pub fn skipped(&self) -> &[YaraError] {
+        &self.skipped
+    }
+
+    fn parse_file(
+        &mut self,
+        cursor: &mut Cursor<'_>,
+        errors: &mut Vec<YaraError>,
+    ) -> Result<(), YaraError> {
+        loop {
+            cursor.skip_trivia();
+            if cursor.is_eof() {
+                return Ok(());
+            }
+            match cursor.word() {
+                "import" | "include" => {
+                    cursor.skip_trivia();
+                    cursor.string()?;
+                }
+                "private" | "global" => {}
+                "rule" => self.parse_rule(cursor, errors)?,
+                _ => return Err(cursor.error(YaraErrorKind::Expected("`rule`"))),
+            }
+        }
+    }
+
+    fn parse_rule(
+        &mut self,
+        cursor: &mut Cursor<'_>,
+        errors: &mut Vec<YaraError>,
+    ) -> Result<(), YaraError> {
+        cursor.skip_trivia();
+        let rule = cursor.word();
+        if rule.is_empty() {
+            return Err(cursor.error(YaraErrorKind::Expected("rule name")));
+        }
+
+        // tags
+        cursor.skip_trivia();
+        if cursor.eat(b':') {
+            loop {
+                cursor.skip_trivia();
+                if cursor.word().is_empty() {
+                    break;
+                }
+            }
+        }
+        cursor.expect(b'{', "`{`")?;
+
+        loop {
+            cursor.skip_trivia();
+            if cursor.eat(b'}') {
+                return Ok(());
+            }
+
+            let section = cursor.word();
+            cursor.skip_trivia();
+            cursor.expect(b':', "`:`")?;
+
+            match section {
+                "meta" => cursor.skip_meta()?,
+                "strings" => self.parse_strings(cursor, rule, errors)?,
+                "condition" => {
+                    cursor.skip_condition()?;
+                    cursor.expect(b'}', "`}`")?;
+                    return Ok(());
+                }
+                _ => return Err(cursor.error(YaraErrorKind::Expected("section"))),
+            }
+        }
+    }
+
+    fn parse_strings(
+        &mut self,
+        cursor: &mut Cursor<'_>,
+        rule: &str,
+        errors: &mut Vec<YaraError>,
+    ) -> Result<(), YaraError> {
+        loop {
+            cursor.skip_trivia();
+            if cursor.peek() != Some(b'$') {
+                return Ok(());
+            }
+
+            let line = cursor.line;
+            let identifier = cursor.word();
+            cursor.skip_trivia();
+            cursor.expect(b'=', "`=`")?;
+            cursor.skip_trivia();
+
+            let column = cursor.column();
+            let result = match cursor.peek() {
+                Some(b'{') => {
+                    let body = cursor.hex_string()?;
+                    expand(body)
+                }
+                Some(b'"') => {
+                    cursor.string()?;
+                    Err(YaraErrorKind::TextString)
+                }
+                Some(b'/') => {
+                    cursor.regex()?;
+                    Err(YaraErrorKind::Regex)
+                }
+                _ => return Err(cursor.error(YaraErrorKind::Expected("string value"))),
+            };
+            cursor.skip_modifiers()?;
+
+            let error = |kind| YaraError { line, kind };
+            match result {
+                Ok(variants) => self.push(rule, identifier, line, column, variants, errors),
+                Err(kind) if kind.is_unsupported() => self.skipped.push(error(kind)),
+                Err(kind) => errors.push(error(kind)),
+            }
+        }
+    }
+
+    fn push(
+        &mut self,
+        rule: &str,
+        identifier: &str,
+        line: usize,
+        column: usize,
+        variants: Vec<String>,
+        errors: &mut Vec<YaraError>,
+    ) {
+        let mut patterns = Vec::with_capacity(variants.len());
+        for variant in &variants {
+            match Pattern::parse_with(variant, Dialect::Native) {
+                Ok(pattern) => patterns.push(pattern),
+                Err(e) => {
+                    errors.push(YaraError {
+                        line,
+                        kind: YaraErrorKind::Pattern(e.with_column(column)),
+                    });
+                    return;
+                }
+            }
+        }
+
+        let owner = self.strings.len();
+        let ids = (self.patterns.len()..self.patterns.len() + patterns.len())
+            .map(PatternId)
+            .collect();
+        self.owners
+            .extend(std::iter::repeat_n(owner, patterns.len()));
+        self.patterns.extend(patterns);
+        self.strings.push(YaraString {
+            rule: rule.to_owned(),
+            identifier: identifier.to_owned(),
+            patterns: ids,
+            line,
+        });
+    }
+}
+
+/// An element of a YARA hex string.
+enum Node {
+    /// A native pattern token: a byte, a negated byte or a jump.
+    Token(String),
+    Alternation(Vec<Vec<Node>>),
+}
+
+impl Node {
+    fn is_byte(&self) -> bool {
+        matches!(self, Node::Token(t) if t.len() == 2)
+    }
+}
+
+/// Translates the body of a hex string into one native pattern per
+/// combination of its multi-byte alternations.
+fn expand(body: &str) -> Result<Vec<String>, YaraErrorKind> {
+    let mut chars = body.as_bytes();
+    let nodes = hex_sequence(&mut chars)?;
+    if !chars.is_empty() {
+        return Err(YaraErrorKind::InvalidHexString);
+    }
+
+    let mut variants = vec![String::new()];
+    append(&mut variants, &nodes)?;
+    Ok(variants)
+}
+
+fn append(variants: &mut Vec<String>, nodes: &[Node]) -> Result<(), YaraErrorKind> {
+    for node in nodes {
+        match node {
+            Node::Token(token) => {
+                for variant in variants.iter_mut() {
+                    variant.push(' ');
+                    variant.push_str(token);
+                }
+            }
+            Node::Alternation(alternatives)
+                if alternatives.iter().all(|a| a.len() == 1 && a[0].is_byte()) =>
+            {
+                let bytes: Vec<_> = alternatives
+                    .iter()
+                    .map(|a| match &a[0] {
+                        Node::Token(t) => t.as_str(),
+                        Node::Alternation(_) => unreachable!(),
+                    })
+                    .collect();
+                let token = format!("({})", bytes.join("|"));
+                for variant in variants.iter_mut() {
+                    variant.push(' ');
+                    variant.push_str(&token);
+                }
+            }
+            Node::Alternation(alternatives) => {
+                let mut expanded = Vec::new();
+                for alternative in alternatives {
+                    let mut branch = variants.clone();
+                    append(&mut branch, alternative)?;
+                    expanded.extend(branch);
+                    if expanded.len() > MAX_ALTERNATIVES {
+                        return Err(YaraErrorKind::TooManyAlternatives);
+                    }
+                }
+                *variants = expanded;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parses hex string elements up to the end of input, a `|` or a `)`.
+fn hex_sequence(chars: &mut &[u8]) -> Result<Vec<Node>, YaraErrorKind> {
+    let is_digit = |c: u8| c.is_ascii_hexdigit() || c == b'?';
+    let mut nodes = Vec::new();
+
+    loop {
+        skip_hex_trivia(chars);
+        match *chars {
+            [] | [b'|' | b')', ..] => return Ok(nodes),
+            [hi, lo, rest @ ..] if is_digit(*hi) && is_digit(*lo) => {
+                nodes.push(Node::Token(format!("{}{}", *hi as char, *lo as char)));
+                *chars = rest;
+            }
+            [b'~', hi, lo, rest @ ..] if is_digit(*hi) && is_digit(*lo) => {
+                nodes.push(Node::Token(format!("!{}{}", *hi as char, *lo as char)));
+                *chars = rest;
+            }
+            [b'[', rest @ ..] => {
+                let end = rest
+                    .iter()
+                    .position(|&c| c == b']')
+                    .ok_or(YaraErrorKind::InvalidHexString)?;
+                let jump = std::str::from_utf8(&rest[..end])
+                    .map_err(|_| YaraErrorKind::InvalidHexString)?;
+                nodes.push(Node::Token(translate_jump(jump)?));
+                *chars = &rest[end + 1..];
+            }
+            [b'(', rest @ ..] => {
+                *chars = rest;
+                let mut alternatives = vec![hex_sequence(chars)?];
+                loop {
+                    match *chars {
+                        [b'|', rest @ ..] => {
+                            *chars = rest;
+                            alternatives.push(hex_sequence(chars)?);
+                        }
+                        [b')', rest @ ..] => {
+                            *chars = rest;
+                            break;
+                        }
+                        _ => return Err(YaraErrorKind::InvalidHexString),
+                    }
+                }
+                if alternatives.iter().any(Vec::is_empty) {
+                    return Err(YaraErrorKind::InvalidHexString);
+                }
+                nodes.push(Node::Alternation(alternatives));
+            }
+            _ => return Err(YaraErrorKind::InvalidHexString),
+        }
+    }
+}
+
+fn skip_hex_trivia(chars: &mut &[u8]) {
+    loop {
+        match *chars {
+            [c, rest @ ..] if c.is_ascii_whitespace() => *chars = rest,
+            [b'/', b'/', rest @ ..] => {
+                let end = rest.iter().position(|&c| c == b'\n').unwrap_or(rest.len());
+                *chars = &rest[end..];
+            }
+            [b'/', b'*', rest @ ..] => {
+                let end = rest
+                    .windows(2)
+                    .position(|w| w == b"*/")
+                    .map_or(rest.len(), |e| e + 2);
+                *chars = &rest[end..];
+            }
+            _ => return,
+        }
+    }
+}
+
+/// Rewrites `[n]`, `[n-m]`, `[n-]` or `[-]` as a native jump.
+fn translate_jump(body: &str) -> Result<String, YaraErrorKind> {
+    let bound = |s: &str| {
+        s.trim()
+            .parse::<usize>()
+            .map_err(|_| YaraErrorKind::InvalidHexString)
+    };
+
+    let (min, max) = match body.split_once('-') {
+        Some((_, hi)) if hi.trim().is_empty() => return Err(YaraErrorKind::UnboundedJump),
+        Some((lo, hi)) if lo.trim().is_empty() => (0, bound(hi)?),
+        Some((lo, hi)) => (bound(lo)?, bound(hi)?),
+        None => (bound(body)?, bound(body)?),
+    };
+    if min > max {
+        return Err(YaraErrorKind::InvalidHexString);
+    }
+    Ok(format!("[{min}-{max}]"))
+}
+
+/// A minimal lexer over YARA source, tracking line numbers.
+struct Cursor<'s> {
+    source: &'s str,
+    pos: usize,
+    line: usize,
+}
+
+impl<'s> Cursor<'s> {
+    fn new(source: &'s str) -> Self {
+        Cursor {
+            source,
+            pos: 0,
+            line: 1,
+        }
+    }
+
+    fn rest(&self) -> &'s [u8] {
+        &self.source.as_bytes()[self.pos..]
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.rest().first().copied()
+    }
+
+    fn is_eof(&self) -> bool {
+        self.pos >= self.source.len()
+    }
+
+    /// Byte offset of the current position within its line.
+    fn column(&self) -> usize {
+        let line_start = self.source[..self.pos].rfind('\n').map_or(0, |i| i + 1);
+        self.pos - line_start
+    }
+
+    fn bump(&mut self) -> Option<u8> {
+        let c = self.peek()?;
+        if c == b'\n' {
+            self.line += 1;
+        }
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        if self.peek() == Some(c) {
+            self.bump();
+            return true;
+        }
+        false
+    }
+
+    fn expect(&mut self, c: u8, what: &'static str) -> Result<(), YaraError> {
+        if self.eat(c) {
+            return Ok(());
+        }
+        Err(self.error(YaraErrorKind::Expected(what)))
+    }
+
+    fn error(&self, kind: YaraErrorKind) -> YaraError {
+        let kind = match kind {
+            YaraErrorKind::Expected(_) if self.is_eof() => YaraErrorKind::UnexpectedEof,
+            kind => kind,
+        };
+        YaraError {
+            line: self.line,
+            kind,
+        }
+    }
+
+    fn skip_trivia(&mut self) {
+        loop {
+            match self.rest() {
+                [c, ..] if c.is_ascii_whitespace() => {
+                    self.bump();
+                }
+                [b'/', b'/', ..] => {
+                    while self.peek().is_some_and(|c| c != b'\n') {
+                        self.bump();
+                    }
+                }
+                [b'/', b'*', ..] => {
+                    self.pos += 2;
+                    while !self.rest().is_empty() && !self.rest().starts_with(b"*/") {
+                        self.bump();
+                    }
+                    self.pos = (self.pos + 2).min(self.source.len());
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Reads an identifier, including a leading `$` and trailing `*`.
+    fn word(&mut self) -> &'s str {
+        let start = self.pos;
+        self.eat(b'$');
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == b'_')
+        {
+            self.bump();
+        }
+        self.eat(b'*');
+        &self.source[start..self.pos]
+    }
+
+    /// Reads a `"..."` literal, honoring escapes.
+    fn string(&mut self) -> Result<(), YaraError> {
+        self.delimited(b'"', "`\"`")
+    }
+
+    /// Reads a `/.../` literal and its flags.
+    fn regex(&mut self) -> Result<(), YaraError> {
+        self.delimited(b'/', "`/`")?;
+        self.word();
+        Ok(())
+    }
+
+    fn delimited(&mut self, delimiter: u8, what: &'static str) -> Result<(), YaraError> {
+        self.expect(delimiter, what)?;
+        loop {
+            match self.bump() {
+                Some(b'\\') => {
+                    self.bump();
+                }
+                Some(c) if c == delimiter => return Ok(()),
+                Some(_) => {}
+                None => return Err(self.error(YaraErrorKind::UnexpectedEof)),
+            }
+        }
+    }
+
+    /// Reads a `{ ... }` hex string, returning its body.
+    fn hex_string(&mut self) -> Result<&'s str, YaraError> {
+        self.expect(b'{', "`{`")?;
+        let start = self.pos;
+        loop {
+            match self.bump() {
+                Some(b'}') => return Ok(&self.source[start..self.pos - 1]),
+                Some(_) => {}
+                None => return Err(self.error(YaraErrorKind::UnexpectedEof)),
+            }
+        }
+    }
+
+    /// Skips string modifiers such as `wide ascii nocase` or `xor(1-255)`.
+    fn skip_modifiers(&mut self) -> Result<(), YaraError> {
+        loop {
+            self.skip_trivia();
+            if !self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+                return Ok(());
+            }
+
+            let before = (self.pos, self.line);
+            let word = self.word();
+            self.skip_trivia();
+            if self.peek() == Some(b':') && matches!(word, "condition" | "meta" | "strings") {
+                (self.pos, self.line) = before;
+                return Ok(());
+            }
+            if self.eat(b'(') {
+                self.skip_until(b')')?;
+            }
+        }
+    }
+
+    /// Skips `key = value` entries up to the next section.
+    fn skip_meta(&mut self) -> Result<(), YaraError> {
+        loop {
+            self.skip_trivia();
+            let before = (self.pos, self.line);
+            let key = self.word();
+            if key.is_empty() {
+                return Err(self.error(YaraErrorKind::Expected("metadata")));
+            }
+            self.skip_trivia();
+            if self.peek() == Some(b':') {
+                (self.pos, self.line) = before;
+                return Ok(());
+            }
+
+            self.expect(b'=', "`=`")?;
+            self.skip_trivia();
+            match self.peek() {
+                Some(b'"') => self.string()?,
+                _ => {
+                    self.eat(b'-');
+                    if self.word().is_empty() {
+                        return Err(self.error(YaraErrorKind::Expected("metadata value")));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Skips a condition up to, but not including, the closing `}` of its rule.
+    fn skip_condition(&mut self) -> Result<(), YaraError> {
+        loop {
+            self.skip_trivia();
+            match self.peek() {
+                Some(b'}') => return Ok(()),
+                Some(b'"') => self.string()?,
+                Some(_) => {
+                    self.bump();
+                }
+                None => return Err(self.error(YaraErrorKind::UnexpectedEof)),
+            }
+        }
+    }
+
+    fn skip_until(&mut self, end: u8) -> Result<(), YaraError> {
+        loop {
+            match self.peek() {
+                Some(b'"') => self.string()?,
+                Some(c) => {
+                    self.bump();
+                    if c == end {
+                        return Ok(());
+                    }
+                }
+                None => return Err(self.error(YaraErrorKind::UnexpectedEof)),
+            }
+        }
+    }
+
+    /// Moves past the current rule to the next `rule` keyword at the start
+    /// of a line, returning `false` at end of input.
+    fn skip_to_rule(&mut self) -> bool {
+        while let Some(c) = self.bump() {
+            if c == b'\n' {
+                let line = self.rest();
+                let trimmed = line.trim_ascii_start();
+                let indent = line.len() - trimmed.len();
+                for keyword in [&b"rule"[..], b"private", b"global"] {
+                    if trimmed.starts_with(keyword) {
+                        for _ in 0..indent {
+                            self.bump();
+                        }
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+}
+
+#[derive(Debug)]
+pub enum YaraErrorKind {
+    /// The rule text does not follow YARA's grammar.
+    Expected(&'static str),
+    UnexpectedEof,
+    InvalidHexString,
+    /// Text strings (`"..."`) are not supported.
+    TextString,
+    /// Regular expressions (`/.../`) are not supported.
+    Regex,
+    /// Jumps without an upper bound (`[n-]`, `[-]`) are not supported.
+    UnboundedJump,
+    /// Alternations expand to more than 64 patterns.
+    TooManyAlternatives,
+    /// The translated hex string does not compile. The pattern no longer
+    /// reads like the hex string, so its column is that of the string's `{`
+    /// within the line.
+    Pattern(PatternError),
+}
+
+impl YaraErrorKind {
+    /// Whether the string was valid YARA but has no pattern equivalent.
+    pub fn is_unsupported(&self) -> bool {
+        matches!(
+            self,
+            YaraErrorKind::TextString
+                | YaraErrorKind::Regex
+                | YaraErrorKind::UnboundedJump
+                | YaraErrorKind::TooManyAlternatives
+        )
+    }
+}
+
+/// An error in a YARA rule file, on the line of the offending rule or
+/// string.
+pub type YaraError = SourceError<YaraErrorKind>;
+
+impl Display for YaraErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            YaraErrorKind::Expected(what) => write!(f, "expected {what}"),
+            YaraErrorKind::UnexpectedEof => write!(f, "unexpected end of file"),
+            YaraErrorKind::InvalidHexString => write!(f, "invalid hex string"),
+            YaraErrorKind::TextString => write!(f, "text strings are not supported"),
+            YaraErrorKind::Regex => write!(f, "regular expressions are not supported"),
+            YaraErrorKind::UnboundedJump => write!(f, "unbounded jumps are not supported"),
+            YaraErrorKind::TooManyAlternatives => {
+                write!(
+                    f,
+                    "alternations expand to more than {MAX_ALTERNATIVES} patterns"
+                )
+            }
+            YaraErrorKind::Pattern(e) => {
+                write!(f, "token {} (column {}): {}", e.token, e.column, e.kind)
+            }
+        }
+    }
+}
+
+impl std::error::Error for YaraErrorKind {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            YaraErrorKind::Pattern(e) => Some(e),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use hexpotter::{CaptureValue, Hexpotter, MatchedPattern, Pattern, PatternErrorKind, Scan};
+
+fn first_match(scanner: &Hexpotter, data: &[u8]) -> MatchedPattern {
+    let mut first = None;
+    scanner.scan(data, |m| {
+        first = Some(m);
+        Scan::Stop
+    });
+    first.unwrap()
+}
+
+#[test]
+fn captures_of_a_shorter_buffer_are_empty() {
+    for pattern in ["48 8B 05 <disp:i32le>", "48 [0-2] 8B 05 <disp:i32le>"] {
+        let scanner = Hexpotter::new([pattern]);
+        let data = [0x48, 0x8B, 0x05, 0xF0, 0xFF, 0xFF, 0xFF];
+        let matched = first_match(&scanner, &data);
+        assert_eq!(
+            scanner.captures(&matched, &data).get("disp"),
+            Some(CaptureValue::I32(-16)),
+            "{pattern}"
+        );
+
+        for len in 0..data.len() {
+            let captures = scanner.captures(&matched, &data[..len]);
+            assert!(captures.is_empty(), "{pattern} {len}");
+        }
+    }
+}
+
+#[test]
+fn captures_decode_each_type_and_byte_order() {
+    let data = [
+        0xB8, 0xFE, 0x12, 0x34, 0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x80,
+    ];
+    let cases = [
+        ("B8 <v:u8>", CaptureValue::U8(0xFE)),
+        ("B8 <v:i8>", CaptureValue::I8(-2)),
+        ("B8 ?? <v:u16>", CaptureValue::U16(0x3412)),
+        ("B8 ?? <v:u16be>", CaptureValue::U16(0x1234)),
+        ("B8 ?? ?? ?? <v:i16le>", CaptureValue::I16(-1)),
+        ("B8 ?? ?? ?? <v:u32>", CaptureValue::U32(0xFFFF_FFFF)),
+        ("B8 ?? ?? ?? <v:i32be>", CaptureValue::I32(-1)),
+        (
+            "B8 ?? ?? ?? ?? ?? ?? ?? <v:i64>",
+            CaptureValue::I64(i64::MIN + 1),
+        ),
+        (
+            "B8 ?? ?? ?? ?? ?? ?? ?? <v:u64be>",
+            CaptureValue::U64(0x0100_0000_0000_0080),
+        ),
+    ];
+    for (pattern, value) in cases {
+        let scanner = Hexpotter::new([pattern]);
+        let matched = first_match(&scanner, &data);
+        let captures = scanner.captures(&matched, &data);
+        assert_eq!(captures.get("v"), Some(value), "{pattern}");
+        assert_eq!(
+            captures.capture("v").unwrap().offset(),
+            pattern.find('<').unwrap() / 3
+        );
+    }
+}
+
+#[test]
+fn captures_follow_the_jumps_before_them() {
+    let scanner = Hexpotter::new(["48 [0-4] 8B 05 <disp:i32> <next:u8>"]);
+    let data = [0x48, 0x90, 0x90, 0x8B, 0x05, 0x10, 0x00, 0x00, 0x00, 0xC3];
+    let matched = first_match(&scanner, &data);
+    let captures = scanner.captures(&matched, &data);
+
+    let names: Vec<(&str, usize)> = captures.iter().map(|c| (c.name(), c.offset())).collect();
+    assert_eq!(names, [("disp", 5), ("next", 9)]);
+    assert_eq!(captures.get("disp"), Some(CaptureValue::I32(0x10)));
+    assert_eq!(captures.get("next"), Some(CaptureValue::U8(0xC3)));
+}
+
+#[test]
+fn malformed_captures_are_rejected() {
+    let cases = [
+        ("48 <disp:i24>", PatternErrorKind::InvalidCapture),
+        ("48 <disp>", PatternErrorKind::InvalidCapture),
+        ("48 <u8be:u8be>", PatternErrorKind::InvalidCapture),
+        ("48 <a:u8> <a:u16>", PatternErrorKind::DuplicateCapture),
+    ];
+    for (pattern, kind) in cases {
+        assert_eq!(
+            Pattern::parse(pattern).unwrap_err().kind(),
+            kind,
+            "{pattern}"
+        );
+    }
+}
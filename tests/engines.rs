@@ -0,0 +1,295 @@
+use std::time::Instant;
+
+use hexpotter::{
+    ByteFrequencies, Dialect, EngineKind, Hexpotter, HexpotterBuilder, PatternId, Scan, SimdPath,
+};
+
+const ENGINES: [EngineKind; 3] = [EngineKind::Scalar, EngineKind::Anchor, EngineKind::Teddy];
+
+type Matches = Vec<(usize, usize, usize)>;
+
+fn scan(patterns: &[String], engine: EngineKind, data: &[u8]) -> Matches {
+    let scanner = Hexpotter::builder()
+        .engine(engine)
+        .build(patterns.iter().map(String::as_str))
+        .unwrap();
+    let mut found = Vec::new();
+    scanner.scan(data, |m| {
+        found.push((m.start(), m.id().usize(), m.end()));
+        Scan::Continue
+    });
+    found
+}
+
+/// Scans `data` with every engine, checking that they report the same matches
+/// in start order, and returns them.
+fn agree(patterns: &[String], data: &[u8]) -> Matches {
+    let expected = scan(patterns, EngineKind::Scalar, data);
+    assert!(expected.is_sorted(), "{expected:?}");
+    for engine in ENGINES {
+        assert_eq!(scan(patterns, engine, data), expected, "{engine:?}");
+    }
+    expected
+}
+
+/// Deterministic bytes drawn from a small alphabet, so patterns built from
+/// it match often.
+fn noise(len: usize, seed: u64) -> Vec<u8> {
+    let mut state = seed | 1;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            [0x00, 0x48, 0x8B, 0xC3, 0xE8, 0xFF][(state % 6) as usize]
+        })
+        .collect()
+}
+
+fn strings(patterns: &[&str]) -> Vec<String> {
+    patterns.iter().map(|p| p.to_string()).collect()
+}
+
+#[test]
+fn engines_agree_on_data_shorter_than_a_block() {
+    let patterns = strings(&["48 8B", "C3", "E8 ?? ?? FF"]);
+    for len in 0..80 {
+        let mut data = noise(len, len as u64);
+        agree(&patterns, &data);
+
+        // a match ending on the last byte sits in the padded tail
+        if len >= 4 {
+            data[len - 4..].copy_from_slice(&[0xE8, 0x00, 0x00, 0xFF]);
+            let found = agree(&patterns, &data);
+            assert!(found.contains(&(len - 4, 2, len)), "{len}");
+        }
+    }
+}
+
+#[test]
+fn engines_agree_across_groups() {
+    // far more keys than the 16 buckets of one Teddy group hold
+    let patterns: Vec<String> = (0..300u32)
+        .map(|i| format!("{:02X} {:02X} 8B", i & 0xFF, i >> 8))
+        .collect();
+    let mut data = noise(4000, 7);
+    for (i, chunk) in data.chunks_mut(16).enumerate() {
+        let i = (i * 37) % 300;
+        chunk[..3].copy_from_slice(&[(i & 0xFF) as u8, (i >> 8) as u8, 0x8B]);
+    }
+
+    let found = agree(&patterns, &data);
+    assert!(found.len() >= 250);
+}
+
+#[test]
+fn engines_agree_on_unanchored_patterns() {
+    let patterns = strings(&["!00 ??", "?? !48", "48 8B"]);
+    for seed in 1..20 {
+        let data = noise(100, seed);
+        let found = agree(&patterns, &data);
+        let unanchored = found.iter().filter(|(_, id, _)| *id == 0).count();
+        let expected = data[..99].iter().filter(|&&b| b != 0x00).count();
+        assert_eq!(unanchored, expected);
+    }
+}
+
+#[test]
+fn engines_agree_on_gapped_patterns() {
+    let patterns = strings(&[
+        "48 [0-3] 8B",
+        "E8 [1-2] C3 [0-2] FF",
+        "?? C3 [2-4] 48 8B",
+        "FF FF [0-8] 00",
+    ]);
+    for seed in 1..20 {
+        agree(&patterns, &noise(300, seed));
+    }
+}
+
+#[test]
+fn engines_report_matches_in_start_order() {
+    // the long pattern is anchored on its last bytes, found after the
+    // anchors of matches that start later
+    let patterns = strings(&["48 48 48 48 48 48 48 48 48 48 90", "48 48"]);
+    let scanner = Hexpotter::try_new(patterns.iter().map(String::as_str)).unwrap();
+    assert!(scanner.anchor(PatternId(0)).offset() > 0);
+
+    let data = [0x48; 10].into_iter().chain([0x90]).collect::<Vec<u8>>();
+    let found = agree(&patterns, &data);
+    assert_eq!(found[0], (0, 0, 11));
+    assert_eq!(found[1], (0, 1, 2));
+}
+
+#[test]
+fn engines_stop_at_the_first_match() {
+    let patterns = strings(&["48 [0-3] 8B", "8B", "!00 C3"]);
+    for seed in 1..20 {
+        let data = noise(200, seed);
+        let expected = scan(&patterns, EngineKind::Scalar, &data);
+        for engine in ENGINES {
+            let scanner = Hexpotter::builder()
+                .engine(engine)
+                .build(patterns.iter().map(String::as_str))
+                .unwrap();
+            let mut found = Vec::new();
+            scanner.scan(&data, |m| {
+                found.push((m.start(), m.id().usize(), m.end()));
+                Scan::Stop
+            });
+            assert_eq!(found.first(), expected.first(), "{engine:?}");
+            assert!(found.len() <= 1, "{engine:?}");
+        }
+    }
+}
+
+#[test]
+fn stop_ends_an_unanchored_scan_early() {
+    let data = vec![0x90; 1 << 20];
+    for patterns in [&["!00"][..], &["!00 !00", "E8 4C"]] {
+        let scanner = Hexpotter::builder()
+            .engine(EngineKind::Anchor)
+            .build(patterns.iter().copied())
+            .unwrap();
+        let timed = |scan: Scan| {
+            let mut calls = 0;
+            let started = Instant::now();
+            scanner.scan(&data, |_| {
+                calls += 1;
+                scan
+            });
+            (started.elapsed(), calls)
+        };
+
+        let (stopped, calls) = timed(Scan::Stop);
+        assert_eq!(calls, 1);
+        let (full, _) = timed(Scan::Continue);
+        assert!(stopped * 20 < full, "{patterns:?}: {stopped:?} vs {full:?}");
+    }
+}
+
+#[test]
+fn engines_agree_on_patterns_sharing_literals() {
+    // every case of `ab` anchors two patterns
+    let patterns = strings(&[r#"i"ab" 00"#, r#"i"ab" ??"#, r#"i"abc""#, "61 62"]);
+    let data = b"ab\0Ab\0aB\0AB\0abc\0ABC\0aBcab";
+    let found = agree(&patterns, data);
+    assert_eq!(found.iter().filter(|(_, id, _)| *id == 0).count(), 4);
+    assert_eq!(found.iter().filter(|(_, id, _)| *id == 1).count(), 7);
+}
+
+#[test]
+fn auto_leaves_teddy_for_too_many_keys() {
+    let strings: Vec<String> = (0..3000)
+        .map(|i: u32| {
+            let letters: String = format!("{i:06}")
+                .bytes()
+                .map(|digit| (digit - b'0' + b'a') as char)
+                .collect();
+            format!(r#"i"{letters}""#)
+        })
+        .collect();
+
+    let few = Hexpotter::try_new(strings[..10].iter().map(String::as_str)).unwrap();
+    let many = Hexpotter::try_new(strings.iter().map(String::as_str)).unwrap();
+    if few.simd() != SimdPath::Scalar {
+        assert_eq!(few.engine(), EngineKind::Teddy);
+    }
+    assert_eq!(many.engine(), EngineKind::Anchor);
+}
+
+#[test]
+fn engines_agree_on_patterns_without_a_fixed_byte() {
+    let patterns = strings(&["?? 4? ?8", "[48 4C] ?? ?B", "!00 C? ?? E?", "80&F0 ?F"]);
+    let scanner = Hexpotter::try_new(patterns.iter().map(String::as_str)).unwrap();
+    // anchored on masked positions rather than verified at every offset
+    for (id, pattern) in patterns.iter().enumerate() {
+        let anchor = scanner.anchor(PatternId(id));
+        assert!(anchor.is_anchored(), "{pattern}");
+        assert!(anchor.literals().len() <= 16, "{pattern}");
+    }
+    assert_eq!(scanner.anchor(PatternId(1)).offset(), 0);
+
+    for seed in 1..20 {
+        let found = agree(&patterns, &noise(300, seed));
+        assert!(found.iter().any(|(_, id, _)| *id == 0), "{seed}");
+    }
+}
+
+#[test]
+fn anchors_follow_the_byte_frequencies() {
+    let pattern = ["00 00 00 ?? E8 4C"];
+    let anchor = |builder: HexpotterBuilder| {
+        let scanner = builder.build(pattern).unwrap();
+        scanner.anchor(PatternId(0)).clone()
+    };
+
+    // `00` is common in code, so the default model avoids it
+    let x86 = anchor(Hexpotter::builder());
+    assert_eq!((x86.offset(), x86.literals()), (4, &[vec![0xE8, 0x4C]][..]));
+
+    // in data full of calls, the zeros are rarer
+    let calls: Vec<u8> = [0xE8, 0x4C, 0x90].repeat(100);
+    let sampled = anchor(Hexpotter::builder().sample(&calls));
+    assert_eq!(sampled.offset(), 0);
+    assert_eq!(
+        sampled,
+        anchor(Hexpotter::builder().byte_frequencies(ByteFrequencies::from_sample(&calls)))
+    );
+
+    // with every byte equally likely, the longest run wins
+    let uniform = anchor(Hexpotter::builder().byte_frequencies(ByteFrequencies::uniform()));
+    assert_eq!((uniform.offset(), uniform.len()), (0, 3));
+
+    let data = [calls.clone(), vec![0, 0, 0, 1, 0xE8, 0x4C]].concat();
+    for builder in [
+        Hexpotter::builder(),
+        Hexpotter::builder().sample(&calls),
+        Hexpotter::builder().byte_frequencies(ByteFrequencies::uniform()),
+    ] {
+        let scanner = builder.build(pattern).unwrap();
+        let mut starts = Vec::new();
+        scanner.scan(&data, |m| {
+            starts.push(m.start());
+            Scan::Continue
+        });
+        assert_eq!(starts, [300]);
+    }
+}
+
+#[test]
+fn builders_report_the_engine_they_built() {
+    let patterns = ["48 8B 05", "E8 ?? ?? ?? ??"];
+    for engine in ENGINES {
+        let scanner = Hexpotter::builder().engine(engine).build(patterns).unwrap();
+        assert_eq!(scanner.engine(), engine);
+        if engine != EngineKind::Teddy {
+            assert_eq!(scanner.simd(), SimdPath::Scalar, "{engine:?}");
+        }
+    }
+
+    // `Auto` settles on a concrete engine, Teddy wherever SIMD is available
+    let auto = Hexpotter::new(patterns);
+    let teddy = Hexpotter::builder()
+        .engine(EngineKind::Teddy)
+        .build(patterns)
+        .unwrap();
+    assert_ne!(auto.engine(), EngineKind::Auto);
+    assert_eq!(auto.simd(), teddy.simd());
+    if teddy.simd() != SimdPath::Scalar {
+        assert_eq!(auto.engine(), EngineKind::Teddy);
+    }
+
+    let ida = Hexpotter::builder()
+        .dialect(Dialect::Ida)
+        .engine(EngineKind::Scalar)
+        .build(["48 8B ? 05"])
+        .unwrap();
+    assert_eq!(ida.engine(), EngineKind::Scalar);
+    assert!(
+        Hexpotter::builder()
+            .dialect(Dialect::Ida)
+            .build(["48 8B 4? 05"])
+            .is_err()
+    );
+}
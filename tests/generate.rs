@@ -0,0 +1,92 @@
+use hexpotter::{Generalizer, GenerateError, Generator, Minimizer, Pattern};
+
+#[test]
+fn minimizing_counts_negated_positions_as_fixed() {
+    let pattern: Pattern = "!00 !01 48".parse().unwrap();
+    let data = [0x05, 0x06, 0x48, 0x00, 0x48];
+
+    let minimized = Minimizer::new(&pattern).sample(&data).minimize().unwrap();
+    assert_eq!(minimized.pattern().to_string(), "?? ?? 48");
+    assert_eq!(minimized.wildcarded(), [0, 1]);
+}
+
+#[test]
+fn generated_patterns_wildcard_displacements_and_list_matches_in_order() {
+    let data = [
+        0xE8, 0x11, 0x22, 0x33, 0x44, 0xC3, // call; ret
+        0xE8, 0x55, 0x66, 0x77, 0x88, 0x90, // call; nop
+        0xE8, 0x99, 0xAA, 0xBB, 0xCC, 0x90, // call; nop
+    ];
+
+    let unique = Generator::new(&data).generate(0).unwrap();
+    assert_eq!(unique.pattern().to_string(), "E8 ?? ?? ?? ?? C3");
+    assert_eq!(unique.matches(), [0]);
+
+    let Err(GenerateError::NotUnique(repeated)) = Generator::new(&data).max_len(6).generate(6)
+    else {
+        panic!("`E8 ?? ?? ?? ?? 90` matches twice");
+    };
+    assert_eq!(repeated.pattern().to_string(), "E8 ?? ?? ?? ?? 90");
+    assert_eq!(repeated.matches(), [6, 12]);
+}
+
+#[test]
+fn generalizing_turns_inserted_bytes_into_jumps() {
+    let v1 = [0x55, 0x48, 0x89, 0xE5, 0x31, 0xC0, 0x5D, 0xC3];
+    let v2 = [0x55, 0x48, 0x89, 0xE5, 0x90, 0x90, 0x31, 0xC0, 0x5D, 0xC3];
+    let v3 = [0xCC, 0x55, 0x48, 0x89, 0xE5, 0x90, 0x31, 0xC0, 0x5D, 0xC3];
+
+    let aligned = Generalizer::new()
+        .sample(&v1, 0)
+        .sample(&v2, 0)
+        .sample(&v3, 1)
+        .gaps(true)
+        .generalize()
+        .unwrap();
+    assert_eq!(
+        aligned.pattern().to_string(),
+        "55 48 89 E5 [0-2] 31 C0 5D C3"
+    );
+    assert!(aligned.is_unique());
+    assert_eq!(aligned.matches(2), [1]);
+
+    let positional = Generalizer::new()
+        .sample(&v1, 0)
+        .sample(&v2, 0)
+        .generalize()
+        .unwrap();
+    assert_eq!(positional.pattern().to_string(), "55 48 89 E5 ?? ?0 ?? C?");
+}
+
+#[test]
+fn generalizing_reports_repeats_and_bad_samples() {
+    let data = [0x48, 0x8B, 0x05, 0x48, 0x8B, 0x05];
+    let repeated = Generalizer::new()
+        .sample(&data, 0)
+        .sample(&data, 3)
+        .max_len(3)
+        .generalize()
+        .unwrap();
+    assert_eq!(repeated.pattern().to_string(), "48 8B 05");
+    assert_eq!(repeated.matches(0), [0, 3]);
+    assert!(!repeated.is_unique());
+
+    assert!(matches!(
+        Generalizer::new().generalize(),
+        Err(GenerateError::NoSamples)
+    ));
+    assert!(matches!(
+        Generalizer::new()
+            .sample(&data, 0)
+            .sample(&data, 6)
+            .generalize(),
+        Err(GenerateError::OutOfBounds)
+    ));
+    assert!(matches!(
+        Generalizer::new()
+            .sample(&[0x00, 0x0F], 0)
+            .sample(&[0xFF, 0xF0], 0)
+            .generalize(),
+        Err(GenerateError::NoCommonBytes)
+    ));
+}
@@ -0,0 +1,190 @@
+use hexpotter::{
+    FlirtErrorKind, FlirtSignatures, LoadError, PatternErrorKind, PatternId, Scan,
+    SignatureErrorKind, Signatures, YaraErrorKind, YaraRules,
+};
+
+#[test]
+fn pattern_errors_are_located_within_the_line() {
+    let errors = Signatures::parse("ok = 90\n  bad = 48 4G  # typo\n").unwrap_err();
+    assert_eq!(errors[0].line(), 2);
+    let SignatureErrorKind::Pattern(e) = errors[0].kind() else {
+        panic!("{}", errors[0]);
+    };
+    assert_eq!(e.kind(), PatternErrorKind::InvalidHexDigit('G'));
+    assert_eq!(e.column(), "  bad = 48 4".len());
+
+    let rules = "rule r {\n  strings:\n    $a = { 4D [0-999999] 5A }\n  condition:\n    $a\n}\n";
+    let errors = YaraRules::parse(rules).unwrap_err();
+    assert_eq!(errors[0].line(), 3);
+    let YaraErrorKind::Pattern(e) = errors[0].kind() else {
+        panic!("{}", errors[0]);
+    };
+    assert_eq!(e.kind(), PatternErrorKind::InvalidJump);
+    assert_eq!(e.column(), "    $a = ".len());
+
+    let errors = FlirtSignatures::parse("   .... 00 0000 0004 :0000 _f\n").unwrap_err();
+    let FlirtErrorKind::Pattern(e) = errors[0].kind() else {
+        panic!("{}", errors[0]);
+    };
+    assert_eq!(e.column(), 3);
+}
+
+#[test]
+fn unreadable_files_are_not_parse_errors() {
+    let missing = std::env::temp_dir().join("hexpotter-missing.sig");
+    assert!(matches!(Signatures::load(&missing), Err(LoadError::Io(_))));
+    assert!(matches!(YaraRules::load(&missing), Err(LoadError::Io(_))));
+    assert!(matches!(
+        FlirtSignatures::load(&missing),
+        Err(LoadError::Io(_))
+    ));
+}
+
+#[test]
+fn crc_lengths_past_the_function_are_invalid_numbers() {
+    for crc_len in ["FFFFFFFFFF", "FFFFFFFFFFFFFFFF", "100", "21"] {
+        let pat = format!("558BEC {crc_len} 0000 0040 :0000 _f ....C3\n");
+        let errors = FlirtSignatures::parse(&pat).unwrap_err();
+        assert!(
+            matches!(errors[0].kind(), FlirtErrorKind::InvalidNumber),
+            "{crc_len}: {}",
+            errors[0]
+        );
+    }
+    assert!(FlirtSignatures::parse("558BEC 20 0000 0040 :0000 _f ....C3\n").is_ok());
+}
+
+#[test]
+fn crc_matches_ida() {
+    // CRC-16/X-25 of "123456789" is 906E, which IDA stores byte-swapped
+    let leading = "41".repeat(32);
+    let signatures = FlirtSignatures::parse(&format!("{leading} 09 6E90 0029 :0000 _f\n")).unwrap();
+    let signature = signatures.get(PatternId(0)).unwrap();
+
+    let mut data = [vec![0x41; 32], b"123456789".to_vec()].concat();
+    assert!(signature.check_crc(&data, 0));
+    data[40] = b'0';
+    assert!(!signature.check_crc(&data, 0));
+}
+
+#[test]
+fn signatures_keep_their_metadata_and_order() {
+    let source = r#"
+# comment
+first = 48 8B 05 ^?? ?? ?? ??  # trailing
+  @tags = a, b,, c
+  @tags = d
+  @module = game.exe
+second = "a # b" 00
+  @note = x = y
+"#;
+    let signatures = Signatures::parse(source).unwrap();
+    assert_eq!(signatures.len(), 2);
+    assert_eq!(signatures.id("second"), Some(PatternId(1)));
+
+    let first = signatures.get(PatternId(0)).unwrap();
+    assert_eq!(first.line(), 3);
+    assert_eq!(first.tags(), ["a", "b", "c", "d"]);
+    assert_eq!(first.metadata("module"), Some("game.exe"));
+    assert_eq!(first.pattern().to_string(), "48 8B 05 ^?? ?? ?? ??");
+
+    let second = signatures.get(PatternId(1)).unwrap();
+    assert_eq!(second.pattern(), &"61 20 23 20 62 00".parse().unwrap());
+    assert_eq!(second.metadata("note"), Some("x = y"));
+}
+
+#[test]
+fn signature_errors_are_collected_per_line() {
+    let source = "@orphan = 1\nno separator\nbad name = 90\nok = 90\nok = C3\n\
+                  broken = 4G\n  @dropped = 1\nfine = 90\n  @k = 1\n  @k = 2\n";
+    let errors = Signatures::parse(source).unwrap_err();
+    let lines: Vec<usize> = errors.iter().map(|e| e.line()).collect();
+    // metadata of the broken signature on line 6 is dropped with it
+    assert_eq!(lines, [1, 2, 3, 5, 6, 10], "{errors:?}");
+
+    assert!(matches!(
+        errors[0].kind(),
+        SignatureErrorKind::OrphanMetadata
+    ));
+    assert!(matches!(
+        errors[1].kind(),
+        SignatureErrorKind::MissingSeparator
+    ));
+    assert!(matches!(errors[2].kind(), SignatureErrorKind::InvalidName));
+    assert!(matches!(errors[3].kind(), SignatureErrorKind::DuplicateName(n) if n == "ok"));
+    assert!(matches!(errors[4].kind(), SignatureErrorKind::Pattern(_)));
+    assert!(matches!(errors[5].kind(), SignatureErrorKind::DuplicateKey(k) if k == "k"));
+}
+
+#[test]
+fn yara_hex_strings_translate_and_expand() {
+    let rules = r#"
+import "pe"
+private rule r : tag {
+    meta:
+        author = "x { }"
+    strings:
+        $neg = { 4D ~5A }                   // negation
+        $jump = { E8 [2] C3 [1-2] 9? }      /* fixed and ranged jumps */
+        $alt = { 48 (8B | 89) 05 }
+        $seq = { 0F (84 | 85 ?? ) 00 } private
+        $text = "MZ" wide
+        $re = /MZ.{2}/
+        $open = { 4D [2-] 5A }
+        $many = { (00 01|02 03) (00 01|02 03) (00 01|02 03) (00 01|02 03)
+                  (00 01|02 03) (00 01|02 03) (00 01|02 03) }
+    condition:
+        any of them
+}
+"#;
+    let rules = YaraRules::parse(rules).unwrap();
+    let skipped: Vec<usize> = rules.skipped().iter().map(|e| e.line()).collect();
+    assert_eq!(skipped, [11, 12, 13, 14]);
+    assert!(rules.skipped().iter().all(|e| e.kind().is_unsupported()));
+    assert!(matches!(
+        rules.skipped()[3].kind(),
+        YaraErrorKind::TooManyAlternatives
+    ));
+
+    let seq = rules.find("r", "$seq").unwrap();
+    assert_eq!(seq.patterns().len(), 2);
+    assert_eq!(rules.find("r", "$alt").unwrap().patterns().len(), 1);
+
+    let data = [
+        0x4D, 0x5B, 0xE8, 0, 0, 0xC3, 0, 0x95, 0x48, 0x89, 0x05, 0x0F, 0x85, 0xAA, 0x00, 0x4D, 0x5A,
+    ];
+    let mut found = Vec::new();
+    rules.scanner().scan(&data, |m| {
+        let string = rules.get(m.id()).unwrap();
+        found.push((string.identifier().to_owned(), m.start()));
+        Scan::Continue
+    });
+    let found: Vec<(&str, usize)> = found.iter().map(|(s, at)| (s.as_str(), *at)).collect();
+    assert_eq!(
+        found,
+        [("$neg", 0), ("$jump", 2), ("$alt", 8), ("$seq", 11)]
+    );
+}
+
+#[test]
+fn malformed_yara_rules_fail_the_import() {
+    let rules = "rule a { strings: $a = { 4D 5 } condition: $a }\n\
+                 rule b { strings: $b = { 4D [3-1] } condition: $b }\n\
+                 rule c { strings: $c = { 4D ( 5A } condition: $c }\n\
+                 rule d { strings: $d = { 4D 5A } condition: $d\n";
+    let errors = YaraRules::parse(rules).unwrap_err();
+    assert!(
+        errors[..3]
+            .iter()
+            .all(|e| matches!(e.kind(), YaraErrorKind::InvalidHexString)),
+        "{errors:?}"
+    );
+    assert_eq!(
+        errors[..3].iter().map(|e| e.line()).collect::<Vec<_>>(),
+        [1, 2, 3]
+    );
+    assert!(
+        matches!(errors[3].kind(), YaraErrorKind::UnexpectedEof),
+        "{errors:?}"
+    );
+}
@@ -0,0 +1,399 @@
+use hexpotter::{
+    CaptureType, Dialect, Endian, EngineKind, Hexpotter, LintKind, Pattern, PatternErrorKind,
+    PatternId, Relative, Scan, lint, pattern::MAX_JUMP,
+};
+
+#[test]
+fn wildcard_only_token_is_an_invalid_wildcard() {
+    for pattern in ["???", "????", "48 ???"] {
+        let errors = Hexpotter::try_new([pattern]).err().unwrap();
+        assert_eq!(
+            errors[0].kind(),
+            PatternErrorKind::InvalidWildcard,
+            "{pattern}"
+        );
+    }
+}
+
+type Matches = Vec<(usize, usize)>;
+
+fn matches(scanner: &Hexpotter, data: &[u8]) -> Matches {
+    let mut found = Vec::new();
+    scanner.scan(data, |m| {
+        found.push((m.start(), m.end()));
+        Scan::Continue
+    });
+    found
+}
+
+#[test]
+fn gapped_matches_do_not_depend_on_the_anchor() {
+    let pattern = ["AA [0-2] BB"];
+    let cases: [(&[u8], Matches); 3] = [
+        (&[0xAA, 0xBB, 0xBB, 0xBB, 0x00], vec![(0, 2)]),
+        (&[0xAA, 0xAA, 0xAA, 0xBB], vec![(0, 4), (1, 4), (2, 4)]),
+        (&[0xAA, 0x00, 0x00, 0x00, 0xBB], vec![]),
+    ];
+
+    for engine in [EngineKind::Scalar, EngineKind::Anchor, EngineKind::Teddy] {
+        let on_first = Hexpotter::builder().engine(engine).build(pattern).unwrap();
+        // `AA` being common makes `BB` the rarer anchor
+        let on_last = Hexpotter::builder()
+            .engine(engine)
+            .sample(&[0xAA; 10])
+            .build(pattern)
+            .unwrap();
+        assert_eq!(on_first.anchor(PatternId(0)).offset(), 0);
+        assert_eq!(on_last.anchor(PatternId(0)).offset(), 1);
+
+        for (data, expected) in &cases {
+            assert_eq!(&matches(&on_first, data), expected, "{engine:?}");
+            assert_eq!(&matches(&on_last, data), expected, "{engine:?}");
+        }
+    }
+}
+
+#[test]
+fn jumps_longer_than_the_cap_are_rejected() {
+    let huge = format!("E8 [0-{}] 48 8B 05", usize::MAX);
+    let too_long = format!("E8 [0-{}] 48", MAX_JUMP + 1);
+    let merged = format!("E8 [0-{MAX_JUMP}] [0-1] 48");
+    for pattern in [&huge, &too_long, &merged] {
+        let errors = Hexpotter::try_new([pattern.as_str()]).err().unwrap();
+        assert_eq!(errors[0].kind(), PatternErrorKind::InvalidJump, "{pattern}");
+    }
+    assert!(Hexpotter::try_new([format!("E8 [0-{MAX_JUMP}] 48").as_str()]).is_ok());
+
+    let built = Pattern::builder()
+        .byte(0xE8)
+        .jump(0, MAX_JUMP + 1)
+        .byte(0x48)
+        .build();
+    assert_eq!(built.unwrap_err().kind(), PatternErrorKind::InvalidJump);
+}
+
+#[test]
+fn patterns_of_negated_bytes_match() {
+    let data = [0x11, 0x90, 0x00, 0x90];
+    for engine in [EngineKind::Scalar, EngineKind::Anchor, EngineKind::Teddy] {
+        let scanner = Hexpotter::builder()
+            .engine(engine)
+            .build(["!00 !01"])
+            .unwrap();
+        assert_eq!(matches(&scanner, &data), [(0, 2), (1, 3)], "{engine:?}");
+    }
+}
+
+#[test]
+fn patterns_differing_only_in_target_are_duplicates() {
+    let patterns: Vec<Pattern> = [
+        "48 8B 05 ?? ?? ?? ?? C3",
+        "48 8B 05 ^?? ?? ?? ?? C3",
+        "48 8B 05 <disp:i32> C3",
+    ]
+    .iter()
+    .map(|p| p.parse().unwrap())
+    .collect();
+
+    let lints: Vec<(usize, LintKind)> = lint(&patterns)
+        .iter()
+        .map(|l| (l.pattern_index(), l.kind()))
+        .collect();
+    assert_eq!(
+        lints,
+        [
+            (1, LintKind::Duplicate { of: 0 }),
+            (2, LintKind::Duplicate { of: 0 })
+        ]
+    );
+}
+
+/// Matches of `AA [0-j] BB [0-j] CC` by plain backtracking over the jumps,
+/// shortest first.
+fn reference(data: &[u8], jump: usize) -> Matches {
+    fn segment(data: &[u8], at: usize, rest: &[u8], jump: usize) -> Option<usize> {
+        let (&byte, rest) = rest.split_first()?;
+        if data.get(at) != Some(&byte) {
+            return None;
+        }
+        if rest.is_empty() {
+            return Some(at + 1);
+        }
+        (0..=jump).find_map(|skip| segment(data, at + 1 + skip, rest, jump))
+    }
+    (0..data.len())
+        .filter_map(|start| Some((start, segment(data, start, &[0xAA, 0xBB, 0xCC], jump)?)))
+        .collect()
+}
+
+#[test]
+fn patterns_with_several_jumps_match_like_plain_backtracking() {
+    let pattern = ["AA [0-16] BB [0-16] CC"];
+    let mut state = 0x2545_F491_4F6C_DD1D_u64;
+    let data: Vec<u8> = (0..3000)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            [0xAA, 0xBB, 0xCC, 0x00, 0x00, 0x00][(state % 6) as usize]
+        })
+        .collect();
+    let expected = reference(&data, 16);
+    assert!(expected.len() > 100);
+
+    for engine in [EngineKind::Scalar, EngineKind::Anchor, EngineKind::Teddy] {
+        let mut offsets = Vec::new();
+        for sample in [&[][..], &[0xAA; 10], &[0xAA, 0xBB, 0xAA, 0xBB, 0xAA, 0xBB]] {
+            let scanner = Hexpotter::builder()
+                .engine(engine)
+                .sample(sample)
+                .build(pattern)
+                .unwrap();
+            offsets.push(scanner.anchor(PatternId(0)).offset());
+            assert_eq!(matches(&scanner, &data), expected, "{engine:?} {sample:?}");
+        }
+        assert_eq!(offsets, [0, 1, 2], "{engine:?}");
+    }
+}
+
+#[test]
+fn dialects_parse_to_the_same_pattern() {
+    let native: Pattern = "48 8B ?? ?? 05".parse().unwrap();
+    let spellings = [
+        ("48 8B ?? ?? 05", Dialect::Native),
+        ("48 8B ? ? 05", Dialect::Ida),
+        ("48 8B * * 05", Dialect::CheatEngine),
+        ("488B????05", Dialect::Compact),
+        (r"\x48\x8B\x00\x00\x05 xx??x", Dialect::Code),
+    ];
+    for (text, dialect) in spellings {
+        assert_eq!(
+            Pattern::parse_with(text, dialect).unwrap(),
+            native,
+            "{text}"
+        );
+        assert_eq!(text.parse::<Pattern>().unwrap(), native, "{text}");
+    }
+    assert_eq!(native.to_string().parse::<Pattern>().unwrap(), native);
+}
+
+#[test]
+fn dialects_reject_what_they_cannot_express() {
+    let cases = [
+        ("48 4?", Dialect::Ida, PatternErrorKind::InvalidWildcard),
+        ("488B?", Dialect::Compact, PatternErrorKind::IncompleteByte),
+        (r"\x48\x", Dialect::Code, PatternErrorKind::IncompleteByte),
+        (
+            "48 4G",
+            Dialect::CheatEngine,
+            PatternErrorKind::InvalidHexDigit('G'),
+        ),
+        ("", Dialect::Native, PatternErrorKind::EmptyPattern),
+    ];
+    for (text, dialect, kind) in cases {
+        let error = Pattern::parse_with(text, dialect).unwrap_err();
+        assert_eq!(error.kind(), kind, "{text}");
+    }
+}
+
+/// The bytes each engine accepts at the first position of `pattern`, whose
+/// other positions must accept `8B`.
+fn accepted(pattern: &str) -> Vec<u8> {
+    let data: Vec<u8> = (0..=255).flat_map(|b| [b, 0x8B]).collect();
+    let mut accepted = Vec::new();
+    for engine in [EngineKind::Scalar, EngineKind::Anchor, EngineKind::Teddy] {
+        let scanner = Hexpotter::builder()
+            .engine(engine)
+            .build([pattern])
+            .unwrap();
+        let bytes: Vec<u8> = matches(&scanner, &data)
+            .into_iter()
+            .filter(|(start, _)| start % 2 == 0)
+            .map(|(start, _)| data[start])
+            .collect();
+        if engine != EngineKind::Scalar {
+            assert_eq!(bytes, accepted, "{pattern} {engine:?}");
+        }
+        accepted = bytes;
+    }
+    accepted
+}
+
+#[test]
+fn classes_and_alternations_accept_their_bytes() {
+    let rex_w: Vec<u8> = (0x48..=0x4F).collect();
+    assert_eq!(accepted("[48-4F] 8B"), rex_w);
+    assert_eq!(accepted("[48 4C 49] 8B"), [0x48, 0x49, 0x4C]);
+    assert_eq!(accepted("[40 48-4A CC] 8B"), [0x40, 0x48, 0x49, 0x4A, 0xCC]);
+    assert_eq!(accepted("(48|4C) 8B"), [0x48, 0x4C]);
+    assert_eq!(accepted("(48|4?) 8B"), (0x40..=0x4F).collect::<Vec<u8>>());
+}
+
+#[test]
+fn masks_compare_only_their_bits() {
+    let modrm: Vec<u8> = (0..=255).filter(|b| b & 0xC7 == 0x83).collect();
+    assert_eq!(accepted("8B&C7 8B"), modrm);
+    assert_eq!(accepted("8B:C7 8B"), modrm);
+
+    let bits: Vec<u8> = (0..=255).filter(|b| b & 0xC7 == 0x45).collect();
+    assert_eq!(accepted("0b01??_?101 8B"), bits);
+
+    let error = Pattern::parse("0b0120_0000").unwrap_err();
+    assert_eq!(error.kind(), PatternErrorKind::InvalidBinaryDigit('2'));
+}
+
+#[test]
+fn target_markers_set_the_reported_target() {
+    let data = [0x90, 0xE8, 0x10, 0x00, 0x00, 0x00, 0xC3];
+    let cases = [
+        ("E8 ^?? ?? ?? ??", 2),
+        ("E8 ^ ?? ?? ?? ??", 2),
+        ("^E8 ?? ?? ?? ??", 1),
+        ("E8 ?? ?? ?? ?? ^", 6),
+        ("E8 ?? ?? ?? ??", 1),
+    ];
+    for (pattern, target) in cases {
+        let scanner = Hexpotter::new([pattern]);
+        let mut targets = Vec::new();
+        scanner.scan(&data, |m| {
+            targets.push(m.target());
+            Scan::Continue
+        });
+        assert_eq!(targets, [target], "{pattern}");
+    }
+
+    let error = Pattern::parse("^E8 ^?? ?? ?? ??").unwrap_err();
+    assert_eq!(error.kind(), PatternErrorKind::DuplicateMarker);
+}
+
+#[test]
+fn strings_expand_to_their_encoded_bytes() {
+    let cases = [
+        (r#""MZ""#, "4D 5A"),
+        (r#""é""#, "C3 A9"),
+        (r#"u"Ké""#, "4B 00 E9 00"),
+        (r#""a b\"\\""#, "61 20 62 22 5C"),
+        (r#""\n\r\t\0\xFF""#, "0A 0D 09 00 FF"),
+        (r#"u"\xFF""#, "FF 00"),
+        (r#"i"a1""#, "[41 61] 31"),
+        (r#"iu"a""#, "[41 61] 00"),
+        (r#"ui"a""#, "[41 61] 00"),
+        (r#"48 "MZ" ??"#, "48 4D 5A ??"),
+    ];
+    for (string, bytes) in cases {
+        assert_eq!(
+            string.parse::<Pattern>().unwrap(),
+            bytes.parse::<Pattern>().unwrap(),
+            "{string}"
+        );
+    }
+}
+
+#[test]
+fn malformed_strings_are_rejected() {
+    let cases = [
+        (r#""MZ"#, PatternErrorKind::UnterminatedString),
+        (r#"48 "MZ"#, PatternErrorKind::UnterminatedString),
+        (r#"x"MZ""#, PatternErrorKind::InvalidStringPrefix),
+        (r#"ii"MZ""#, PatternErrorKind::InvalidStringPrefix),
+        (r#""\q""#, PatternErrorKind::InvalidEscape),
+        (r#""\xG0""#, PatternErrorKind::InvalidEscape),
+        (r#""""#, PatternErrorKind::EmptyGroup),
+    ];
+    for (pattern, kind) in cases {
+        let error = Pattern::parse(pattern).unwrap_err();
+        assert_eq!(error.kind(), kind, "{pattern}");
+    }
+}
+
+#[test]
+fn built_patterns_equal_their_parsed_spelling() {
+    let built = Pattern::builder()
+        .bytes(&[0x48, 0x8B])
+        .wildcard()
+        .nibble_hi(0x4)
+        .nibble_lo(0x5)
+        .masked(0x83, 0xC7)
+        .any_of(&[0x4C, 0x48])
+        .none_of(&[0x00])
+        .jump(1, 3)
+        .target()
+        .byte(0xE8)
+        .capture("disp", CaptureType::I32(Endian::Little))
+        .wildcards(2)
+        .follow(Relative::CALL)
+        .build()
+        .unwrap();
+    let parsed: Pattern = "48 8B ?? 4? ?5 83&C7 [48 4C] !00 [1-3] ^E8 <disp:i32> ?? ?? => call"
+        .parse()
+        .unwrap();
+    assert_eq!(built, parsed);
+    assert_eq!(built.to_string().parse::<Pattern>().unwrap(), built);
+}
+
+#[test]
+fn built_patterns_are_validated() {
+    let cases = [
+        (Pattern::builder().build(), PatternErrorKind::EmptyPattern),
+        (
+            Pattern::builder().target().build(),
+            PatternErrorKind::EmptyPattern,
+        ),
+        (
+            Pattern::builder().jump(0, 2).byte(0x48).build(),
+            PatternErrorKind::MisplacedJump,
+        ),
+        (
+            Pattern::builder().byte(0x48).jump(0, 2).build(),
+            PatternErrorKind::MisplacedJump,
+        ),
+        (
+            Pattern::builder()
+                .byte(0x48)
+                .jump(0, MAX_JUMP + 1)
+                .byte(0x8B)
+                .build(),
+            PatternErrorKind::InvalidJump,
+        ),
+        (
+            Pattern::builder()
+                .capture("a", CaptureType::U8)
+                .capture("a", CaptureType::U8)
+                .build(),
+            PatternErrorKind::DuplicateCapture,
+        ),
+    ];
+    for (i, (built, kind)) in cases.into_iter().enumerate() {
+        assert_eq!(built.unwrap_err().kind(), kind, "{i}");
+    }
+}
+
+#[test]
+fn macro_patterns_equal_parsed_ones() {
+    use hexpotter::{StaticPattern, pattern};
+
+    const PATTERNS: [(StaticPattern, &str); 5] = [
+        (pattern!("48 8B ?? 05"), "48 8B ?? 05"),
+        (pattern!("48 8B ? * 05"), "48 8B ?? ?? 05"),
+        (pattern!("4? ?5 83&C7"), "4? ?5 83&C7"),
+        (pattern!("E8 ^?? ?? ?? ??"), "E8 ^?? ?? ?? ??"),
+        (pattern!("  ^ 90  "), "^90"),
+    ];
+    for (constant, text) in PATTERNS {
+        assert_eq!(Pattern::from(constant), text.parse().unwrap(), "{text}");
+    }
+
+    let data = [0x90, 0xE8, 0x10, 0x00, 0x00, 0x00, 0x48, 0x8B, 0x00, 0x05];
+    let scanner = Hexpotter::from_patterns(PATTERNS.map(|(constant, _)| constant));
+    let parsed = Hexpotter::new(PATTERNS.map(|(_, text)| text));
+    let found = |scanner: &Hexpotter| {
+        let mut found = Vec::new();
+        scanner.scan(&data, |m| {
+            found.push((m.id(), m.start(), m.target()));
+            Scan::Continue
+        });
+        found
+    };
+    assert_eq!(found(&scanner), found(&parsed));
+    assert!(found(&scanner).contains(&(PatternId(3), 1, 2)));
+}
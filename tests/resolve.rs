@@ -0,0 +1,83 @@
+use hexpotter::{Hexpotter, MatchedPattern, Pattern, PatternErrorKind, Relative, Scan};
+
+fn first_match(scanner: &Hexpotter, data: &[u8]) -> MatchedPattern {
+    let mut first = None;
+    scanner.scan(data, |m| {
+        first = Some(m);
+        Scan::Stop
+    });
+    first.unwrap()
+}
+
+#[test]
+fn chains_follow_each_step_in_order() {
+    // 0x00: je 0x0A; 0x0A: jmp short 0x08; 0x08: ...
+    let data = [
+        0x0F, 0x84, 0x04, 0x00, 0x00, 0x00, 0x90, 0x90, 0x90, 0x90, 0xEB, 0xFC,
+    ];
+    let cases = [
+        ("0F 84 ?? ?? ?? ??", Some(0x00)),
+        ("0F 84 ?? ?? ?? ?? => jcc", Some(0x0A)),
+        ("0F 84 ?? ?? ?? ?? => jcc => jmp8", Some(0x08)),
+        (
+            "0F 84 ?? ?? ?? ?? => rel(2, 4, 6) => rel(1, 1, 2)",
+            Some(0x08),
+        ),
+        // the displacement of `rip` starts at the target
+        ("0F 84 ^?? ?? ?? ?? => rip", Some(0x0A)),
+    ];
+    for (pattern, expected) in cases {
+        let scanner = Hexpotter::new([pattern]);
+        let matched = first_match(&scanner, &data);
+        assert_eq!(scanner.resolve(&matched, &data), expected, "{pattern}");
+    }
+}
+
+#[test]
+fn targets_outside_the_buffer_do_not_resolve() {
+    // call -0x10, landing before the buffer
+    let data = [0xE8, 0xF0, 0xFF, 0xFF, 0xFF, 0xE8, 0x00];
+    let scanner = Hexpotter::new(["E8 ?? ?? ?? ?? => call"]);
+    let matched = first_match(&scanner, &data);
+    assert_eq!(scanner.resolve(&matched, &data), None);
+    assert_eq!(scanner.resolve_va(&matched, &data, 0x1000), None);
+
+    // the next displacement is cut off by the end of the buffer
+    let scanner = Hexpotter::new(["E8 ?? ?? ?? ?? => call => call"]);
+    let data = [0xE8, 0x00, 0x00, 0x00, 0x00, 0xE8, 0x00];
+    let matched = first_match(&scanner, &data);
+    assert_eq!(scanner.resolve(&matched, &data), None);
+    assert_eq!(matched.resolve(&data, Relative::CALL), Some(5));
+}
+
+#[test]
+fn virtual_addresses_do_not_overflow() {
+    let data = [0xEB, 0x10];
+    let scanner = Hexpotter::new(["EB ?? => jmp8"]);
+    let matched = first_match(&scanner, &data);
+    assert_eq!(scanner.resolve(&matched, &data), Some(0x12));
+    assert_eq!(scanner.resolve_va(&matched, &data, 0x1000), Some(0x1012));
+    assert_eq!(
+        scanner.resolve_va(&matched, &data, u64::MAX - 0x12),
+        Some(u64::MAX)
+    );
+    assert_eq!(scanner.resolve_va(&matched, &data, u64::MAX - 0x11), None);
+}
+
+#[test]
+fn malformed_chains_are_rejected() {
+    for pattern in [
+        "E8 ?? ?? ?? ?? =>",
+        "E8 ?? ?? ?? ?? => call =>",
+        "E8 ?? ?? ?? ?? => call jmp",
+        "E8 ?? ?? ?? ?? => ret",
+        "E8 ?? ?? ?? ?? => rel(1, 3, 5)",
+        "E8 ?? ?? ?? ?? => rel(1, 4)",
+    ] {
+        assert_eq!(
+            Pattern::parse(pattern).unwrap_err().kind(),
+            PatternErrorKind::InvalidResolveStep,
+            "{pattern}"
+        );
+    }
+}